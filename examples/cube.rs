@@ -2,7 +2,7 @@ extern crate rier;
 #[macro_use] extern crate glium;
 extern crate cgmath;
 use glium::DrawParameters;
-use cgmath::Rad;
+use rier::context::FrameGuard;
 
 
 #[derive(Copy, Clone)]
@@ -113,8 +113,8 @@ impl Cube {
         rier::Mesh::with_indices(renderer, &vertices, &indices).unwrap()
     }
 
-    fn render(&self, renderer: &Renderer, camera: &rier::Camera3D) {
-        renderer.draw(&self.mesh, &uniform! {
+    fn render(&self, frame: &mut FrameGuard, renderer: &Renderer, camera: &rier::Camera3D) {
+        renderer.draw(frame, &self.mesh, &uniform! {
             camera: camera,
             transform: &self.transform
         }).unwrap();
@@ -124,29 +124,32 @@ impl Cube {
 
 fn main()
 {
-    let gfx = rier::Context::create("Cube", (800, 600)).gfx();
+    let gfx = rier::ContextBuilder::new()
+        .title("Cube")
+        .dimensions((800, 600))
+        .multisampling(4)
+        .build()
+        .unwrap()
+        .gfx();
     let renderer = Renderer::new(gfx.clone()).unwrap();
     let mut camera = rier::Camera3D::new(gfx.clone());
     camera.eye = cgmath::Point3::new(4.0, 3.0, 3.0);
-    let mut cube = Cube::new(&renderer);
-    let mut x = 0.0f32;
-    let main_loop = rier::Loop::new(move |delta| {
+    camera.update();
+    let cube = Cube::new(&renderer);
+    let mut orbit = rier::OrbitController::from_camera(&camera);
+    let main_loop = rier::Loop::new(move |time| {
         use rier::main_loop::Return::*;
 
-        camera.update();
-        x += delta.subsec_nanos() as f32 / 1000000000.0;
-        cube.transform.set_rotation(Rad(x), Rad(x), Rad(0.0));
-        cube.transform.dirty();
-        for event in gfx.display.poll_events() {
-            match event {
-                rier::WindowEvent::Closed => return Exit,
-                _ => (),
-            }
+        gfx.pump_events().unwrap();
+        if gfx.close_requested() {
+            return Exit;
         }
-        gfx.frame(|| {
-            cube.render(&renderer, &camera);
+        orbit.update(&gfx.input(), time.delta());
+        orbit.apply(&mut camera);
+        gfx.frame(|frame| {
+            cube.render(frame, &renderer, &camera);
         }).unwrap();
         Next
     });
-    main_loop.start();
+    main_loop.start().unwrap();
 }
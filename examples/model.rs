@@ -8,6 +8,7 @@ use std::io::Read;
 use wavefront_obj::obj;
 use glium::DrawParameters;
 use cgmath::{Rad, Point3};
+use rier::context::FrameGuard;
 
 
 type Mesh = rier::Mesh<Vertex>;
@@ -157,7 +158,11 @@ impl Model {
         Mesh::new(renderer, &*vertices).unwrap()
     }
 
-    fn render(&self, renderer: &Renderer, camera: &rier::Camera3D, light: Point3<f32>) {
+    fn render(&self,
+              frame: &mut FrameGuard,
+              renderer: &Renderer,
+              camera: &rier::Camera3D,
+              light: Point3<f32>) {
         type Point = [f32; 3];
         let light: Point = light.into();
         let view_pos: Point = camera.eye.into();
@@ -168,7 +173,7 @@ impl Model {
             light_pos: light,
             view_pos: view_pos,
         };
-        renderer.draw(&self.mesh, &uniforms).unwrap();
+        renderer.draw(frame, &self.mesh, &uniforms).unwrap();
     }
 }
 
@@ -178,25 +183,23 @@ fn main() {
     let renderer = Renderer::new(gfx.clone()).unwrap();
     let mut camera = rier::Camera3D::new(gfx.clone());
     camera.eye = Point3::new(4.0, 3.0, 3.0);
+    camera.update();
     let mut model = Model::new(&renderer);
     let mut x = 0.0f32;
-    let main_loop = rier::Loop::new(move |delta| {
+    let main_loop = rier::Loop::new(move |time| {
         use rier::main_loop::Return::*;
 
-        camera.update();
-        x += delta.subsec_nanos() as f32 / 1000000000.0;
+        x += time.delta_secs();
         model.transform.set_rotation(Rad(x), Rad(x), Rad(0.0));
         model.transform.dirty();
-        for event in gfx.display.poll_events() {
-            match event {
-                rier::WindowEvent::Closed => return Exit,
-                _ => (),
-            }
+        gfx.pump_events().unwrap();
+        if gfx.close_requested() {
+            return Exit;
         }
-        gfx.frame(|| {
-            model.render(&renderer, &camera, Point3::new(2.2, 1.0, 2.0));
+        gfx.frame(|frame| {
+            model.render(frame, &renderer, &camera, Point3::new(2.2, 1.0, 2.0));
         }).unwrap();
         Next
     });
-    main_loop.start();
+    main_loop.start().unwrap();
 }
@@ -49,21 +49,19 @@ fn main() {
     let renderer = rier::Renderer::<Shader>::new(gfx.clone()).unwrap();
     let mut camera = rier::Camera3D::new(gfx.clone());
     camera.eye = cgmath::Point3::new(4.0, 3.0, 3.0);
+    camera.update();
     let mesh = rier::Mesh::new(&renderer, &[
             Vertex { position: [-1.0, -1.0], color: [0.0, 1.0, 0.0] },
             Vertex { position: [ 0.0,  1.0], color: [0.0, 0.0, 1.0] },
             Vertex { position: [ 1.0, -1.0], color: [1.0, 0.0, 0.0] },
         ]).unwrap();
     'main: loop {
-        camera.update();
-        for event in gfx.display.poll_events() {
-            match event {
-                rier::WindowEvent::Closed => break 'main,
-                _ => (),
-            }
+        gfx.pump_events().unwrap();
+        if gfx.close_requested() {
+            break 'main;
         }
-        gfx.frame(|| {
-            renderer.draw(&mesh, &uniform! { matrix: &camera }).unwrap();
+        gfx.frame(|frame| {
+            renderer.draw(frame, &mesh, &uniform! { matrix: &camera }).unwrap();
         }).unwrap();
     }
 }
@@ -0,0 +1,621 @@
+//! Named actions and axes bound to inputs.
+//!
+//! Game code asks for "jump" or "move_x" instead of checking keys, so the
+//! bindings can be changed by the player and saved to a config file.
+//!
+//! # Example
+//!
+//! ```
+//! use rier::action::ActionMap;
+//! use rier::input::Input;
+//!
+//! let config = "
+//! ## Player controls.
+//! action jump = Space, Mouse:Right
+//! action save = Ctrl+S
+//! axis move_x = A/D, Left/Right, Pad:LeftX
+//! axis look_x = MouseX*0.1
+//! deadzone move_x = 0.2
+//! ";
+//! let actions: ActionMap = config.parse().unwrap();
+//!
+//! let input = Input::new();
+//! assert!(!actions.is_pressed(&input, "jump"));
+//! assert_eq!(actions.axis(&input, "move_x"), 0.0);
+//!
+//! // Saved and loaded back unchanged.
+//! assert_eq!(actions.to_string().parse::<ActionMap>().unwrap(), actions);
+//! ```
+//!
+//! # Config format
+//!
+//! One binding per line, `#` starts a comment:
+//!
+//! - `action <name> = <chord>, ...` binds an action to chords. A chord is
+//!   modifiers and a source joined by `+`, e.g. `Ctrl+Shift+S`.
+//! - `axis <name> = <binding>, ...` binds an axis. A binding is two sources
+//!   for the negative and positive directions, `A/D`, or an analog input
+//!   with an optional scale, `MouseY*-0.5`.
+//! - `deadzone <name> = <value>` sets the dead zone of an axis.
+//!
+//! Sources are `Key` names, like `Space` or `F1`, mouse buttons,
+//! `Mouse:Left`, `Mouse:Right`, `Mouse:Middle` or `Mouse:<number>`, and
+//! gamepad buttons, `Pad:` and a `gamepad::Button` name like `Pad:South`.
+//! Modifiers are `Shift`, `Ctrl`, `Alt` and `Logo`. Analog inputs are
+//! `MouseX`, `MouseY`, `ScrollX`, `ScrollY` and gamepad axes, `Pad:` and a
+//! `gamepad::Axis` name like `Pad:LeftX`.
+//!
+//! Gamepad bindings read all connected gamepads.
+use std::io::{self, Read, Write};
+use std::fs::File;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::collections::BTreeMap;
+use input::{Input, Key, Modifiers, MouseButton};
+use gamepad::{self, GamepadState};
+
+
+/// Digital input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Key(Key),
+    Mouse(MouseButton),
+    /// Button of any gamepad.
+    Gamepad(gamepad::Button),
+}
+
+
+/// Returns `true` if the test passes for any connected gamepad.
+fn any_gamepad<F: Fn(&GamepadState) -> bool>(input: &Input, f: F) -> bool {
+    input.gamepads().into_iter().filter_map(|id| input.gamepad(id)).any(|pad| f(pad))
+}
+
+
+impl Source {
+    fn is_held(&self, input: &Input) -> bool {
+        match *self {
+            Source::Key(key) => input.is_held(key),
+            Source::Mouse(button) => input.is_button_held(button),
+            Source::Gamepad(button) => any_gamepad(input, |pad| pad.is_held(button)),
+        }
+    }
+
+    fn is_pressed(&self, input: &Input) -> bool {
+        match *self {
+            Source::Key(key) => input.is_pressed(key),
+            Source::Mouse(button) => input.is_button_pressed(button),
+            Source::Gamepad(button) => any_gamepad(input, |pad| pad.is_pressed(button)),
+        }
+    }
+
+    fn is_released(&self, input: &Input) -> bool {
+        match *self {
+            Source::Key(key) => input.is_released(key),
+            Source::Mouse(button) => input.is_button_released(button),
+            Source::Gamepad(button) => any_gamepad(input, |pad| pad.is_released(button)),
+        }
+    }
+}
+
+
+/// A source with the modifiers that must be held with it.
+///
+/// Other held modifiers are ignored, unless a chord of the same source
+/// with more modifiers is bound and held: the most specific chord wins.
+///
+/// # Example
+///
+/// ```
+/// use rier::action::ActionMap;
+/// use rier::input::{Input, InputEvent, KeyEvent, Key, Modifiers};
+///
+/// let actions: ActionMap = "action save = Ctrl+S\naction back = S".parse().unwrap();
+/// let ctrl = Modifiers { ctrl: true, ..Modifiers::default() };
+/// let mut input = Input::new();
+/// input.begin_frame();
+/// input.handle(&InputEvent::KeyDown(KeyEvent {
+///     key: Some(Key::S),
+///     scancode: 31,
+///     modifiers: ctrl,
+/// }));
+/// assert!(actions.is_pressed(&input, "save"));
+/// assert!(!actions.is_pressed(&input, "back"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chord {
+    pub modifiers: Modifiers,
+    pub source: Source,
+}
+
+
+impl Chord {
+    /// Chord without modifiers.
+    pub fn new(source: Source) -> Chord {
+        Chord {
+            modifiers: Modifiers::default(),
+            source: source,
+        }
+    }
+
+    fn modifiers_held(&self, input: &Input) -> bool {
+        includes(input.modifiers(), self.modifiers)
+    }
+
+    /// Returns `true` if the other chord has the same source and more
+    /// modifiers.
+    fn is_narrowed_by(&self, other: &Chord) -> bool {
+        other.source == self.source && other.modifiers != self.modifiers &&
+        includes(other.modifiers, self.modifiers)
+    }
+
+    fn is_held(&self, input: &Input) -> bool {
+        self.source.is_held(input) && self.modifiers_held(input)
+    }
+
+    fn is_pressed(&self, input: &Input) -> bool {
+        self.source.is_pressed(input) && self.modifiers_held(input)
+    }
+
+    fn is_released(&self, input: &Input) -> bool {
+        // The modifiers may be released first.
+        self.source.is_released(input)
+    }
+}
+
+
+/// Returns `true` if all modifiers of `b` are in `a`.
+fn includes(a: Modifiers, b: Modifiers) -> bool {
+    (a.shift || !b.shift) && (a.ctrl || !b.ctrl) && (a.alt || !b.alt) && (a.logo || !b.logo)
+}
+
+
+impl From<Key> for Chord {
+    fn from(key: Key) -> Chord {
+        Chord::new(Source::Key(key))
+    }
+}
+
+
+impl From<MouseButton> for Chord {
+    fn from(button: MouseButton) -> Chord {
+        Chord::new(Source::Mouse(button))
+    }
+}
+
+
+/// Analog input, per frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Analog {
+    /// Cursor movement in pixels, right is positive.
+    MouseX,
+    /// Cursor movement in pixels, down is positive.
+    MouseY,
+    /// Scrolled lines, right is positive.
+    ScrollX,
+    /// Scrolled lines, up is positive.
+    ScrollY,
+    /// Axis of the gamepad that moved it the furthest.
+    Gamepad(gamepad::Axis),
+}
+
+
+impl Analog {
+    fn value(&self, input: &Input) -> f32 {
+        match *self {
+            Analog::MouseX => input.mouse_delta().0 as f32,
+            Analog::MouseY => input.mouse_delta().1 as f32,
+            Analog::ScrollX => input.scroll().0,
+            Analog::ScrollY => input.scroll().1,
+            Analog::Gamepad(axis) => {
+                input.gamepads()
+                    .into_iter()
+                    .filter_map(|id| input.gamepad(id))
+                    .map(|pad| pad.axis(axis))
+                    .fold(0.0, |a, b| if b.abs() > a.abs() { b } else { a })
+            }
+        }
+    }
+}
+
+
+/// Input of an axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AxisBinding {
+    /// `-1` while the first source is held, `1` while the second is.
+    Sources(Source, Source),
+    /// Analog value multiplied by the scale.
+    Analog(Analog, f32),
+}
+
+
+impl AxisBinding {
+    fn value(&self, input: &Input) -> f32 {
+        match *self {
+            AxisBinding::Sources(negative, positive) => {
+                let mut value = 0.0;
+                if negative.is_held(input) {
+                    value -= 1.0;
+                }
+                if positive.is_held(input) {
+                    value += 1.0;
+                }
+                value
+            }
+            AxisBinding::Analog(analog, scale) => analog.value(input) * scale,
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+struct Axis {
+    bindings: Vec<AxisBinding>,
+    dead_zone: f32,
+}
+
+
+/// Bindings of named actions and axes.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ActionMap {
+    actions: BTreeMap<String, Vec<Chord>>,
+    axes: BTreeMap<String, Axis>,
+}
+
+
+impl ActionMap {
+    pub fn new() -> ActionMap {
+        ActionMap::default()
+    }
+
+    /// Adds a binding to the action.
+    ///
+    /// Names must be valid in the config format: not empty, without
+    /// whitespace, `#`, `=` or `,`. Other names are rejected, they would
+    /// not load back.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rier::action::{ActionMap, ConfigError};
+    /// use rier::input::Key;
+    ///
+    /// let mut actions = ActionMap::new();
+    /// actions.bind("jump", Key::Space).unwrap();
+    /// match actions.bind("jump high", Key::J) {
+    ///     Err(ConfigError::Name(name)) => assert_eq!(name, "jump high"),
+    ///     _ => panic!("name accepted"),
+    /// }
+    /// assert!(actions.bindings("jump high").is_empty());
+    ///
+    /// // The rejected name left nothing that would not load back.
+    /// assert_eq!(actions.to_string().parse::<ActionMap>().unwrap(), actions);
+    /// ```
+    pub fn bind<C: Into<Chord>>(&mut self, action: &str, chord: C) -> Result<(), ConfigError> {
+        try!(check_name(action));
+        self.actions.entry(action.to_string()).or_insert_with(Vec::new).push(chord.into());
+        Ok(())
+    }
+
+    /// Removes all bindings of the action.
+    pub fn unbind(&mut self, action: &str) {
+        self.actions.remove(action);
+    }
+
+    /// Bindings of the action.
+    pub fn bindings(&self, action: &str) -> &[Chord] {
+        self.actions.get(action).map(|chords| &chords[..]).unwrap_or(&[])
+    }
+
+    /// Adds a binding to the axis, names are checked like in `bind`.
+    pub fn bind_axis(&mut self, axis: &str, binding: AxisBinding) -> Result<(), ConfigError> {
+        try!(check_name(axis));
+        self.axis_entry(axis).bindings.push(binding);
+        Ok(())
+    }
+
+    /// Removes all bindings of the axis, keeps the dead zone.
+    pub fn unbind_axis(&mut self, axis: &str) {
+        let unused = match self.axes.get_mut(axis) {
+            Some(axis) => {
+                axis.bindings.clear();
+                axis.dead_zone == 0.0
+            }
+            None => false,
+        };
+        // Not saved, so it would not load back.
+        if unused {
+            self.axes.remove(axis);
+        }
+    }
+
+    /// Bindings of the axis.
+    pub fn axis_bindings(&self, axis: &str) -> &[AxisBinding] {
+        self.axes.get(axis).map(|axis| &axis.bindings[..]).unwrap_or(&[])
+    }
+
+    /// Sets the value under which the axis reads `0`, default `0`.
+    ///
+    /// Names are checked like in `bind`.
+    pub fn set_dead_zone(&mut self, axis: &str, dead_zone: f32) -> Result<(), ConfigError> {
+        try!(check_name(axis));
+        self.axis_entry(axis).dead_zone = dead_zone;
+        Ok(())
+    }
+
+    fn axis_entry(&mut self, axis: &str) -> &mut Axis {
+        self.axes.entry(axis.to_string()).or_insert_with(|| {
+            Axis {
+                bindings: Vec::new(),
+                dead_zone: 0.0,
+            }
+        })
+    }
+
+    /// Returns `true` if a more specific chord of any action is held, so
+    /// `S` does not match while `Ctrl+S` does.
+    fn is_shadowed(&self, chord: &Chord, input: &Input) -> bool {
+        self.actions
+            .values()
+            .flat_map(|chords| chords.iter())
+            .any(|other| chord.is_narrowed_by(other) && other.modifiers_held(input))
+    }
+
+    /// Returns `true` if any binding of the action is held.
+    pub fn is_held(&self, input: &Input, action: &str) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|chord| chord.is_held(input) && !self.is_shadowed(chord, input))
+    }
+
+    /// Returns `true` if any binding of the action was pressed this frame.
+    pub fn is_pressed(&self, input: &Input, action: &str) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|chord| chord.is_pressed(input) && !self.is_shadowed(chord, input))
+    }
+
+    /// Returns `true` if any binding of the action was released this frame.
+    pub fn is_released(&self, input: &Input, action: &str) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|chord| chord.is_released(input) && !self.is_shadowed(chord, input))
+    }
+
+    /// Sum of the axis bindings, `0` within the dead zone.
+    ///
+    /// Bindings of sources give a value in `[-1, 1]`, analog bindings are
+    /// not clamped.
+    pub fn axis(&self, input: &Input, axis: &str) -> f32 {
+        let axis = match self.axes.get(axis) {
+            Some(axis) => axis,
+            None => return 0.0,
+        };
+        let value: f32 = axis.bindings.iter().map(|binding| binding.value(input)).sum();
+        if value.abs() <= axis.dead_zone { 0.0 } else { value }
+    }
+
+    /// Loads bindings from a config file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<ActionMap, ConfigError> {
+        let mut text = String::new();
+        try!(try!(File::open(path)).read_to_string(&mut text));
+        text.parse()
+    }
+
+    /// Saves bindings to a config file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = try!(File::create(path));
+        file.write_all(self.to_string().as_bytes())
+    }
+}
+
+
+impl FromStr for ActionMap {
+    type Err = ConfigError;
+
+    fn from_str(text: &str) -> Result<ActionMap, ConfigError> {
+        let mut map = ActionMap::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: &str| ConfigError::Parse(index + 1, message.to_string());
+            let (head, value) = match line.find('=') {
+                Some(i) => (line[..i].trim(), line[i + 1..].trim()),
+                None => return Err(error("expected `=`")),
+            };
+            let mut head = head.split_whitespace();
+            let (kind, name) = match (head.next(), head.next(), head.next()) {
+                (Some(kind), Some(name), None) => (kind, name),
+                _ => return Err(error("expected `<kind> <name> =`")),
+            };
+            match kind {
+                "action" => {
+                    for chord in value.split(',') {
+                        let chord = try!(parse_chord(chord.trim()).ok_or_else(|| error("bad chord")));
+                        try!(map.bind(name, chord).map_err(|_| error("bad name")));
+                    }
+                }
+                "axis" => {
+                    for binding in value.split(',') {
+                        let binding = try!(parse_axis_binding(binding.trim())
+                            .ok_or_else(|| error("bad axis binding")));
+                        try!(map.bind_axis(name, binding).map_err(|_| error("bad name")));
+                    }
+                }
+                "deadzone" => {
+                    let dead_zone = try!(value.parse::<f32>().map_err(|_| error("bad number")));
+                    try!(map.set_dead_zone(name, dead_zone).map_err(|_| error("bad name")));
+                }
+                _ => return Err(error("expected `action`, `axis` or `deadzone`")),
+            }
+        }
+        Ok(map)
+    }
+}
+
+
+impl fmt::Display for ActionMap {
+    /// Writes the config format.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, chords) in &self.actions {
+            let chords: Vec<String> = chords.iter().map(chord_name).collect();
+            try!(writeln!(f, "action {} = {}", name, chords.join(", ")));
+        }
+        for (name, axis) in &self.axes {
+            if !axis.bindings.is_empty() {
+                let bindings: Vec<String> = axis.bindings.iter().map(axis_binding_name).collect();
+                try!(writeln!(f, "axis {} = {}", name, bindings.join(", ")));
+            }
+            if axis.dead_zone != 0.0 {
+                try!(writeln!(f, "deadzone {} = {}", name, axis.dead_zone));
+            }
+        }
+        Ok(())
+    }
+}
+
+
+/// Fails if the name would not parse back from the config format.
+fn check_name(name: &str) -> Result<(), ConfigError> {
+    let separator = |c: char| c.is_whitespace() || c == '#' || c == '=' || c == ',';
+    if name.is_empty() || name.contains(separator) {
+        Err(ConfigError::Name(name.to_string()))
+    } else {
+        Ok(())
+    }
+}
+
+
+fn parse_source(s: &str) -> Option<Source> {
+    if s.starts_with("Pad:") {
+        s["Pad:".len()..].parse().ok().map(Source::Gamepad)
+    } else if s.starts_with("Mouse:") {
+        let button = match &s["Mouse:".len()..] {
+            "Left" => MouseButton::Left,
+            "Right" => MouseButton::Right,
+            "Middle" => MouseButton::Middle,
+            n => MouseButton::Other(match n.parse() {
+                Ok(n) => n,
+                Err(_) => return None,
+            }),
+        };
+        Some(Source::Mouse(button))
+    } else {
+        s.parse().ok().map(Source::Key)
+    }
+}
+
+
+fn source_name(source: &Source) -> String {
+    match *source {
+        Source::Key(key) => key.name().to_string(),
+        Source::Mouse(MouseButton::Other(n)) => format!("Mouse:{}", n),
+        Source::Mouse(button) => format!("Mouse:{:?}", button),
+        Source::Gamepad(button) => format!("Pad:{}", button.name()),
+    }
+}
+
+
+fn parse_chord(s: &str) -> Option<Chord> {
+    let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+    let source = match parts.pop().and_then(parse_source) {
+        Some(source) => source,
+        None => return None,
+    };
+    let mut modifiers = Modifiers::default();
+    for part in parts {
+        match part {
+            "Shift" => modifiers.shift = true,
+            "Ctrl" => modifiers.ctrl = true,
+            "Alt" => modifiers.alt = true,
+            "Logo" => modifiers.logo = true,
+            _ => return None,
+        }
+    }
+    Some(Chord {
+        modifiers: modifiers,
+        source: source,
+    })
+}
+
+
+fn chord_name(chord: &Chord) -> String {
+    let mut name = String::new();
+    let modifiers = [(chord.modifiers.shift, "Shift+"),
+                     (chord.modifiers.ctrl, "Ctrl+"),
+                     (chord.modifiers.alt, "Alt+"),
+                     (chord.modifiers.logo, "Logo+")];
+    for &(held, prefix) in &modifiers {
+        if held {
+            name.push_str(prefix);
+        }
+    }
+    name.push_str(&source_name(&chord.source));
+    name
+}
+
+
+fn parse_analog(s: &str) -> Option<Analog> {
+    match s {
+        "MouseX" => Some(Analog::MouseX),
+        "MouseY" => Some(Analog::MouseY),
+        "ScrollX" => Some(Analog::ScrollX),
+        "ScrollY" => Some(Analog::ScrollY),
+        s if s.starts_with("Pad:") => s["Pad:".len()..].parse().ok().map(Analog::Gamepad),
+        _ => None,
+    }
+}
+
+
+fn parse_axis_binding(s: &str) -> Option<AxisBinding> {
+    if let Some(i) = s.find('/') {
+        let negative = parse_source(s[..i].trim());
+        let positive = parse_source(s[i + 1..].trim());
+        return match (negative, positive) {
+            (Some(negative), Some(positive)) => Some(AxisBinding::Sources(negative, positive)),
+            _ => None,
+        };
+    }
+    let (analog, scale) = match s.find('*') {
+        Some(i) => (s[..i].trim(), s[i + 1..].trim().parse().ok()),
+        None => (s, Some(1.0)),
+    };
+    match (parse_analog(analog), scale) {
+        (Some(analog), Some(scale)) => Some(AxisBinding::Analog(analog, scale)),
+        _ => None,
+    }
+}
+
+
+fn axis_binding_name(binding: &AxisBinding) -> String {
+    match *binding {
+        AxisBinding::Sources(ref negative, ref positive) => {
+            format!("{}/{}", source_name(negative), source_name(positive))
+        }
+        AxisBinding::Analog(Analog::Gamepad(axis), scale) => {
+            format!("Pad:{}*{}", axis.name(), scale)
+        }
+        AxisBinding::Analog(analog, scale) => format!("{:?}*{}", analog, scale),
+    }
+}
+
+
+/// Errors which can occur when loading or binding.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// File read failure.
+    Io(io::Error),
+    /// Syntax error, with the line number and a message.
+    Parse(usize, String),
+    /// Action or axis name that can not be written in the config format.
+    Name(String),
+}
+
+
+impl From<io::Error> for ConfigError {
+    fn from(err: io::Error) -> ConfigError {
+        ConfigError::Io(err)
+    }
+}
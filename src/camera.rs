@@ -1,36 +1,71 @@
 //! Camera.
+//!
+//! Cameras follow the context framebuffer size, the projection is updated
+//! when the window resizes.
+use std::rc::Rc;
+use std::cell::Cell;
+use std::time::Duration;
+use std::f32::consts::FRAC_PI_2;
 use num::One;
-use cgmath::{Ortho, PerspectiveFov, Rad, Deg, Point3, vec3};
+use cgmath::{Ortho, PerspectiveFov, Rad, Deg, Point3, Vector3, Vector4, SquareMatrix, InnerSpace,
+             vec3};
 use glium::uniforms::{AsUniformValue, UniformValue};
 use context::Gfx;
+use event::{Return, Subscription};
+use input::{Input, MouseButton};
+use main_loop::secs;
 use transform::Transform;
 use utils::AsMatrix;
 use Matrix;
 
 
+/// Shared framebuffer size, updated by the context resize notification
+/// while the subscription is kept.
+fn viewport(gfx: &Gfx) -> (Rc<Cell<(u32, u32)>>, Subscription) {
+    let viewport = Rc::new(Cell::new(gfx.dimensions()));
+    let shared = viewport.clone();
+    let subscription = gfx.on_resize(move |resize| {
+        shared.set(resize.dimensions);
+        Return::Next
+    });
+    (viewport, subscription.scoped())
+}
+
+
+/// A minimised window has an empty framebuffer, no projection can be built
+/// for it.
+fn is_empty((w, h): (u32, u32)) -> bool {
+    w == 0 || h == 0
+}
+
+
 /// Orthogonal 2D Camera.
 ///
 /// Converts screen coordinate to OpenGL world coordinate.
 pub struct Camera2D {
-    gfx: Gfx,
     transform: Transform,
-    matrix: Matrix,
+    viewport: Rc<Cell<(u32, u32)>>,
+    built: Cell<(u32, u32)>,
+    matrix: Cell<Matrix>,
+    _resize: Subscription,
 }
 
 
 impl Camera2D {
     pub fn new(gfx: Gfx) -> Camera2D {
-        let transform = Transform::new();
-
-        Camera2D {
-            matrix: Camera2D::build_matrix(&gfx, transform.matrix()),
-            gfx: gfx,
-            transform: transform,
-        }
+        let (viewport, resize) = viewport(&gfx);
+        let mut camera = Camera2D {
+            transform: Transform::new(),
+            viewport: viewport,
+            built: Cell::new((0, 0)),
+            matrix: Cell::new(Matrix::one()),
+            _resize: resize,
+        };
+        camera.update();
+        camera
     }
 
-    fn build_matrix(gfx: &Gfx, transform: &Matrix) -> Matrix {
-        let (w, h) = gfx.display.get_framebuffer_dimensions();
+    fn build_matrix((w, h): (u32, u32), transform: &Matrix) -> Matrix {
         let ortho = Ortho {
             left: 0.0,
             right: w as f32,
@@ -42,50 +77,77 @@ impl Camera2D {
         Matrix::from(ortho) * transform
     }
 
-    pub fn update(&mut self) {
-        self.matrix = Camera2D::build_matrix(&self.gfx, self.transform.matrix());
+    /// Returns the matrix for the current framebuffer size, rebuilt if the
+    /// framebuffer resized.
+    ///
+    /// The last matrix is kept while the framebuffer is empty.
+    pub fn matrix(&self) -> Matrix {
+        let dimensions = self.viewport.get();
+        if self.built.get() != dimensions && !is_empty(dimensions) {
+            self.built.set(dimensions);
+            self.matrix.set(Camera2D::build_matrix(dimensions, self.transform.matrix()));
+        }
+        self.matrix.get()
     }
-}
 
+    /// Converts a position in window pixels, from the top left, to world
+    /// coordinate.
+    pub fn screen_to_world(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        let (_, h) = self.viewport.get();
+        let inverse = self.transform.matrix().invert().unwrap_or_else(Matrix::one);
+        let world = inverse * Vector4::new(x, h as f32 - y, 0.0, 1.0);
+        (world.x, world.y)
+    }
 
-impl AsMatrix for Camera2D {
-    fn matrix(&self) -> &Matrix {
-        &self.matrix
+    /// Rebuilds the matrix.
+    ///
+    /// The matrix follows the framebuffer size without calling this.
+    pub fn update(&mut self) {
+        // Rebuilt by `matrix` once the framebuffer is not empty.
+        self.built.set((0, 0));
+        self.matrix();
     }
 }
 
 
 /// Perspective 3D camera.
+///
+/// Call `update` after modifying the fields.
 pub struct Camera3D {
-    gfx: Gfx,
     pub pov: Deg<f32>,
     pub near: f32,
     pub far: f32,
     pub eye: Point3<f32>,
     pub center: Point3<f32>,
-    matrix: Matrix,
+    viewport: Rc<Cell<(u32, u32)>>,
+    built: Cell<(u32, u32)>,
+    matrix: Cell<Matrix>,
+    _resize: Subscription,
 }
 
 
 impl Camera3D {
     pub fn new(gfx: Gfx) -> Camera3D {
+        let (viewport, resize) = viewport(&gfx);
         let mut camera = Camera3D {
-            gfx: gfx,
             pov: Deg(45.0),
             near: 0.1,
             far: 100.0,
             eye: Point3::new(0.0, 0.0, 1.0),
             center: Point3::new(0.0, 0.0, 0.0),
-            matrix: Matrix::one(),
+            viewport: viewport,
+            built: Cell::new((0, 0)),
+            matrix: Cell::new(Matrix::one()),
+            _resize: resize,
         };
         camera.update();
         camera
     }
 
-    fn build_matrix(&self) -> Matrix {
+    fn build_matrix(&self, (w, h): (u32, u32)) -> Matrix {
         let persp = PerspectiveFov {
             fovy: Rad::from(self.pov),
-            aspect: self.aspect(),
+            aspect: w as f32 / h as f32,
             near: self.near,
             far: self.far,
         };
@@ -93,32 +155,207 @@ impl Camera3D {
         Matrix::from(persp) * view
     }
 
-    fn aspect(&self) -> f32 {
-        let (w, h) = self.gfx.display.get_framebuffer_dimensions();
-        w as f32 / h as f32
+    /// Returns the aspect ratio of the current framebuffer, 1 while it is
+    /// empty.
+    pub fn aspect(&self) -> f32 {
+        let (w, h) = self.viewport.get();
+        if is_empty((w, h)) {
+            1.0
+        } else {
+            w as f32 / h as f32
+        }
     }
 
+    /// Returns the matrix for the current framebuffer size, rebuilt if the
+    /// framebuffer resized.
+    ///
+    /// The last matrix is kept while the framebuffer is empty.
+    pub fn matrix(&self) -> Matrix {
+        let dimensions = self.viewport.get();
+        if self.built.get() != dimensions && !is_empty(dimensions) {
+            self.built.set(dimensions);
+            self.matrix.set(self.build_matrix(dimensions));
+        }
+        self.matrix.get()
+    }
+
+    /// Rebuilds the matrix, must be called after modifying the fields.
     pub fn update(&mut self) {
-        self.matrix = self.build_matrix();
+        // Rebuilt by `matrix` once the framebuffer is not empty.
+        self.built.set((0, 0));
+        self.matrix();
+    }
+}
+
+
+/// Orbits a `Camera3D` around a target point by mouse input.
+///
+/// Dragging rotates around the target, scrolling moves closer or further,
+/// dragging with the pan button moves the target. Movement is smoothed
+/// by `damping`, so the camera keeps moving shortly after a fast drag.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use rier::OrbitController;
+/// use rier::input::{Input, InputEvent, ScrollDelta};
+///
+/// let mut orbit = OrbitController::new();
+/// orbit.damping = 0.0;
+/// orbit.max_distance = 20.0;
+///
+/// let mut input = Input::new();
+/// input.begin_frame();
+/// input.handle(&InputEvent::Scroll(ScrollDelta::Lines(0.0, -100.0)));
+/// orbit.update(&input, Duration::from_millis(16));
+/// assert_eq!(orbit.distance, 20.0);
+/// // orbit.apply(&mut camera);
+/// ```
+pub struct OrbitController {
+    /// Point looked at, default origin.
+    pub target: Point3<f32>,
+    /// Distance from the target, default `5`.
+    pub distance: f32,
+    /// Rotation around the vertical axis, `0` looks along `-z`.
+    pub yaw: Rad<f32>,
+    /// Elevation above the target plane.
+    pub pitch: Rad<f32>,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    /// Pitch limits, default just under straight down and up.
+    pub min_pitch: Rad<f32>,
+    pub max_pitch: Rad<f32>,
+    /// Radians per dragged pixel.
+    pub rotate_speed: f32,
+    /// Distance fraction per scrolled line.
+    pub zoom_speed: f32,
+    /// Target movement per dragged pixel, relative to the distance.
+    pub pan_speed: f32,
+    /// How fast movement settles, per second. `0` disables smoothing.
+    pub damping: f32,
+    pub rotate_button: MouseButton,
+    pub pan_button: MouseButton,
+    /// Movement not applied yet.
+    yaw_pending: f32,
+    pitch_pending: f32,
+    zoom_pending: f32,
+    pan_pending: Vector3<f32>,
+}
+
+
+impl OrbitController {
+    pub fn new() -> OrbitController {
+        let limit = FRAC_PI_2 - 0.01;
+        OrbitController {
+            target: Point3::new(0.0, 0.0, 0.0),
+            distance: 5.0,
+            yaw: Rad(0.0),
+            pitch: Rad(0.0),
+            min_distance: 0.5,
+            max_distance: 100.0,
+            min_pitch: Rad(-limit),
+            max_pitch: Rad(limit),
+            rotate_speed: 0.005,
+            zoom_speed: 0.1,
+            pan_speed: 0.002,
+            damping: 10.0,
+            rotate_button: MouseButton::Left,
+            pan_button: MouseButton::Middle,
+            yaw_pending: 0.0,
+            pitch_pending: 0.0,
+            zoom_pending: 0.0,
+            pan_pending: Vector3::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    /// Creates a controller that keeps the current view of the camera.
+    pub fn from_camera(camera: &Camera3D) -> OrbitController {
+        let offset = camera.eye - camera.center;
+        let distance = offset.magnitude();
+        let mut orbit = OrbitController::new();
+        orbit.target = camera.center;
+        if distance > 0.0 {
+            orbit.distance = distance;
+            orbit.yaw = Rad(offset.x.atan2(offset.z));
+            orbit.pitch = Rad((offset.y / distance).asin());
+        }
+        orbit
+    }
+
+    /// Camera position.
+    pub fn eye(&self) -> Point3<f32> {
+        let (yaw, pitch) = (self.yaw.0, self.pitch.0);
+        let direction = vec3(pitch.cos() * yaw.sin(), pitch.sin(), pitch.cos() * yaw.cos());
+        self.target + direction * self.distance
+    }
+
+    /// Reads the input of this frame and moves by the elapsed game time.
+    pub fn update(&mut self, input: &Input, delta: Duration) {
+        let (dx, dy) = input.mouse_delta();
+        let (dx, dy) = (dx as f32, dy as f32);
+        if input.is_button_held(self.rotate_button) {
+            self.yaw_pending -= dx * self.rotate_speed;
+            self.pitch_pending += dy * self.rotate_speed;
+        }
+        if input.is_button_held(self.pan_button) {
+            let yaw = self.yaw.0;
+            let right = vec3(yaw.cos(), 0.0, -yaw.sin());
+            let up = (self.eye() - self.target).normalize().cross(right);
+            let scale = self.pan_speed * self.distance;
+            self.pan_pending += (right * -dx + up * dy) * scale;
+        }
+        self.zoom_pending -= input.scroll().1 * self.zoom_speed;
+
+        // Part of the pending movement applied this frame.
+        let part = if self.damping > 0.0 {
+            1.0 - (-self.damping * secs(delta)).exp()
+        } else {
+            1.0
+        };
+        self.yaw = Rad(self.yaw.0 + self.yaw_pending * part);
+        self.pitch = Rad(self.pitch.0 + self.pitch_pending * part);
+        self.distance *= (self.zoom_pending * part).exp();
+        self.target += self.pan_pending * part;
+        self.yaw_pending *= 1.0 - part;
+        self.pitch_pending *= 1.0 - part;
+        self.zoom_pending *= 1.0 - part;
+        self.pan_pending *= 1.0 - part;
+
+        if self.pitch < self.min_pitch || self.pitch > self.max_pitch {
+            self.pitch = Rad(self.pitch.0.max(self.min_pitch.0).min(self.max_pitch.0));
+            self.pitch_pending = 0.0;
+        }
+        if self.distance < self.min_distance || self.distance > self.max_distance {
+            self.distance = self.distance.max(self.min_distance).min(self.max_distance);
+            self.zoom_pending = 0.0;
+        }
+    }
+
+    /// Moves the camera to the orbit position, looking at the target.
+    pub fn apply(&self, camera: &mut Camera3D) {
+        camera.eye = self.eye();
+        camera.center = self.target;
+        camera.update();
     }
 }
 
 
-impl AsMatrix for Camera3D {
-    fn matrix(&self) -> &Matrix {
-        &self.matrix
+impl Default for OrbitController {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 
 impl<'a> AsUniformValue for &'a Camera2D {
     fn as_uniform_value(&self) -> UniformValue {
-        self.array().as_uniform_value()
+        UniformValue::Mat4(*self.matrix().as_ref())
     }
 }
 
 impl<'a> AsUniformValue for &'a Camera3D {
     fn as_uniform_value(&self) -> UniformValue {
-        self.array().as_uniform_value()
+        UniformValue::Mat4(*self.matrix().as_ref())
     }
 }
@@ -1,12 +1,23 @@
 //! Manage OpenGL context and window.
 use std::string::ToString;
 use std::rc::Rc;
-use std::ops::Deref;
-use std::cell::{UnsafeCell, RefCell, RefMut, Ref};
-use glium::{Display, DisplayBuild};
-use glium::glutin::WindowBuilder;
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use std::cell::{Cell, Ref, RefCell, RefMut};
+use glium::{Display, DisplayBuild, GliumCreationError};
+use glium::glutin::{self, WindowBuilder, HeadlessRendererBuilder, GlRequest, Api, CreationError};
+use glium::glutin::{ElementState, MouseScrollDelta, VirtualKeyCode};
+use glium::texture::RawImage2d;
+use image::RgbaImage;
+use utils::raw_to_image;
+use event::{Notifier, Return, Subscription, NotifyError};
+use gamepad::Backend;
+use input::{Input, InputEvent, InputFrame, KeyEvent, Key, Modifiers, MouseButton, ScrollDelta};
 
 pub use glium::{Frame, Surface, SwapBuffersError, DrawError};
+pub use glium::glutin::GlProfile;
+pub use glium::glutin::Event as WindowEvent;
 
 
 /// Just a reference of `Context`.
@@ -35,28 +46,81 @@ impl Deref for Gfx {
 /// Manage `glium::Display` context and current frame.
 pub struct Context {
     pub display: Display,
-    frame: UnsafeCell<Option<RefCell<Frame>>>,
+    in_frame: Cell<bool>,
     clear_color: (f32, f32, f32, f32),
+    dimensions: Cell<(u32, u32)>,
+    hidpi_factor: Cell<f32>,
+    resize: RefCell<Notifier<Resize>>,
+    input: RefCell<Notifier<InputEvent>>,
+    input_state: RefCell<Input>,
+    input_frame: Cell<InputFrame>,
+    gamepad_backend: RefCell<Option<Box<Backend>>>,
+    /// Held modifier keys, bits by `modifier_bit`.
+    modifier_keys: Cell<u8>,
+    close_requested: Cell<bool>,
 }
 
 
 impl Context {
     /// Builds OpenGL context and create a window.
-    pub fn create<T: ToString>(title: T, (width, height): (u32, u32)) -> Context {
-        let display = WindowBuilder::new()
-            .with_title(title.to_string())
-            .with_dimensions(width, height)
-            .with_depth_buffer(24)
-            .with_vsync()
-            .build_glium()
-            .unwrap();
+    ///
+    /// Use `ContextBuilder` for more settings.
+    /// # Panics
+    /// Panic if the context could not be created.
+    pub fn create<T: ToString>(title: T, dimensions: (u32, u32)) -> Context {
+        ContextBuilder::new().title(title).dimensions(dimensions).build().unwrap()
+    }
+
+    /// Builds OpenGL context without a window.
+    ///
+    /// Renders into an offscreen buffer of the given size, useful for tests
+    /// and server-side rendering.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rier::Context;
+    ///
+    /// let gfx = Context::headless((800, 600)).gfx();
+    /// gfx.frame(|_| {}).unwrap();
+    /// ```
+    ///
+    /// # Panics
+    /// Panic if the context could not be created.
+    pub fn headless(dimensions: (u32, u32)) -> Context {
+        ContextBuilder::new().dimensions(dimensions).build_headless().unwrap()
+    }
+
+    fn new(display: Display) -> Context {
+        let dimensions = display.get_framebuffer_dimensions();
+        let hidpi_factor = display.get_window().map(|w| w.hidpi_factor()).unwrap_or(1.0);
+        // Headless contexts are for tests, which should not read real pads.
+        let gamepad_backend = if display.get_window().is_some() {
+            default_gamepad_backend()
+        } else {
+            None
+        };
         Context {
             display: display,
-            frame: UnsafeCell::new(None),
+            in_frame: Cell::new(false),
             clear_color: (0.0, 0.0, 0.0, 0.0),
+            dimensions: Cell::new(dimensions),
+            hidpi_factor: Cell::new(hidpi_factor),
+            resize: RefCell::new(Notifier::new()),
+            input: RefCell::new(Notifier::new()),
+            input_state: RefCell::new(Input::new()),
+            input_frame: Cell::new(InputFrame::Pump),
+            gamepad_backend: RefCell::new(gamepad_backend),
+            modifier_keys: Cell::new(0),
+            close_requested: Cell::new(false),
         }
     }
 
+    /// Returns `true` if the context has no window.
+    pub fn is_headless(&self) -> bool {
+        self.display.get_window().is_none()
+    }
+
     /// Sets clear color.
     pub fn clear_color(self, r: f32, g: f32, b: f32, a: f32) -> Context {
         Context { clear_color: (r, g, b, a), ..self }
@@ -67,82 +131,731 @@ impl Context {
         Gfx::new(self)
     }
 
-    unsafe fn get_cell(&self) -> &mut Option<RefCell<Frame>> {
-        self.frame.get().as_mut().unwrap()
+    /// Starts a new frame, cleared with the clear color.
+    ///
+    /// The frame ends when the guard is dropped or `FrameGuard::finish`
+    /// is called. Only one frame can exist at a time.
+    pub fn begin_frame(&self) -> Result<FrameGuard, FrameError> {
+        if self.in_frame.get() {
+            return Err(FrameError::Nested);
+        }
+        let mut frame = self.display.draw();
+        frame.clear_color_and_depth(self.clear_color, 1.0);
+        self.in_frame.set(true);
+        Ok(FrameGuard {
+            ctx: self,
+            frame: Some(frame),
+        })
+    }
+
+    /// Returns `true` if a frame has started and not ended yet.
+    pub fn in_frame(&self) -> bool {
+        self.in_frame.get()
+    }
+
+    /// Start a new frame and auto end it.
+    pub fn frame<F>(&self, f: F) -> Result<(), FrameError>
+        where F: FnOnce(&mut FrameGuard)
+    {
+        let mut frame = try!(self.begin_frame());
+        f(&mut frame);
+        frame.finish()
+    }
+
+    /// Reads the pixels of the last finished frame.
+    pub fn capture(&self) -> RgbaImage {
+        let raw: RawImage2d<u8> = self.display.read_front_buffer();
+        raw_to_image(raw)
+    }
+
+    /// Saves the last finished frame to a file.
+    ///
+    /// The image format is deduced from the path extension, e.g. `shot.png`.
+    pub fn screenshot<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.capture().save(path)
+    }
+
+    /// Returns the ratio between the backing framebuffer resolution
+    /// and the window size in screen pixels.
+    ///
+    /// Always `1.0` for a headless context.
+    pub fn hidpi_factor(&self) -> f32 {
+        self.hidpi_factor.get()
+    }
+
+    /// Returns the framebuffer size in pixels, as of the last `poll_events`.
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.dimensions.get()
+    }
+
+    /// Register a callback called when the framebuffer size or the HiDPI
+    /// factor changes.
+    ///
+    /// Callbacks must not register new resize callbacks.
+    pub fn on_resize<F>(&self, callback: F) -> Subscription
+        where F: 'static + FnMut(&Resize) -> Return<Resize>
+    {
+        self.resize.borrow_mut().register(callback)
+    }
+
+    /// The notifier of window input, called by `pump_events`.
+    ///
+    /// Callbacks must not borrow it again.
+    pub fn input_notifier(&self) -> RefMut<Notifier<InputEvent>> {
+        self.input.borrow_mut()
+    }
+
+    /// Keyboard and mouse state, updated by `pump_events`.
+    pub fn input(&self) -> Ref<Input> {
+        self.input_state.borrow()
+    }
+
+    /// Sets when the `input` state starts a new frame, default
+    /// `InputFrame::Pump`.
+    ///
+    /// # Example
+    ///
+    /// A fixed timestep loop sees each press in exactly one update:
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use rier::{Context, Loop};
+    /// use rier::input::{InputFrame, Key};
+    /// use rier::main_loop::Return;
+    ///
+    /// let gfx = Context::create("Fixed", (800, 600)).gfx();
+    /// gfx.set_input_frame(InputFrame::Manual);
+    /// Loop::fixed(Duration::from_millis(10),
+    ///             |_| {
+    ///                 if gfx.input().is_pressed(Key::Space) {
+    ///                     // Jump once, even with two updates in a frame.
+    ///                 }
+    ///                 gfx.begin_input_frame();
+    ///                 Return::Next
+    ///             },
+    ///             |_| {
+    ///                 gfx.pump_events().unwrap();
+    ///                 Return::Next
+    ///             })
+    ///     .start()
+    ///     .unwrap();
+    /// ```
+    pub fn set_input_frame(&self, frame: InputFrame) {
+        self.input_frame.set(frame);
+    }
+
+    /// Clears the per-frame `input` state, held keys and buttons stay held.
+    pub fn begin_input_frame(&self) {
+        self.input_state.borrow_mut().begin_frame();
+    }
+
+    /// Sets the source of gamepad events read by `pump_events`.
+    ///
+    /// Windowed contexts read `joystick::JoystickBackend` on Linux, no
+    /// backend is set elsewhere.
+    pub fn set_gamepad_backend<B: 'static + Backend>(&self, backend: B) {
+        *self.gamepad_backend.borrow_mut() = Some(Box::new(backend));
+    }
+
+    /// Returns `true` if the window close was requested.
+    pub fn close_requested(&self) -> bool {
+        self.close_requested.get()
+    }
+
+    /// Reads all window and gamepad events received since the last call and
+    /// notifies them to the input callbacks.
+    ///
+    /// Resize callbacks are notified before the `InputEvent::Resize`.
+    /// The `input` state starts a new frame, see `set_input_frame`, and is
+    /// updated before the callbacks are called.
+    ///
+    /// All events are notified even if notifying one fails, the first
+    /// error is returned.
+    pub fn pump_events(&self) -> Result<(), NotifyError> {
+        let mut events: Vec<InputEvent> = self.display
+            .poll_events()
+            .filter_map(|event| self.translate(event))
+            .collect();
+        if let Some(ref mut backend) = *self.gamepad_backend.borrow_mut() {
+            events.extend(backend.poll().into_iter().map(InputEvent::Gamepad));
+        }
+        let injected = self.inject(events);
+        let resized = self.check_resize().and_then(|resize| match resize {
+            Some(resize) => self.input.borrow_mut().notify(InputEvent::Resize(resize)),
+            None => Ok(()),
+        });
+        injected.and(resized)
     }
 
-    /// Start a new frame.
-    fn start_frame(&self) {
-        unsafe {
-            let mut cell = self.get_cell();
-            if cell.is_some() {
-                println!("Frame has already started.");
-            } else {
-                let mut frame = self.display.draw();
-                frame.clear_color_and_depth(self.clear_color, 1.0);
-                *cell = Some(RefCell::new(frame));
+    /// Notifies events as if they were received from the window, in place
+    /// of `pump_events`.
+    ///
+    /// The `input` state starts a new frame, unless the `InputFrame` is
+    /// `Manual`. Used to replay recorded input.
+    ///
+    /// An `InputEvent::Resize` updates the size and notifies resize
+    /// callbacks first, like a window resize.
+    pub fn inject(&self, events: Vec<InputEvent>) -> Result<(), NotifyError> {
+        if self.input_frame.get() == InputFrame::Pump {
+            self.begin_input_frame();
+        }
+        let mut result = Ok(());
+        for event in events {
+            match event {
+                InputEvent::Close => self.close_requested.set(true),
+                InputEvent::Resize(resize) => result = result.and(self.apply_resize(resize)),
+                _ => (),
             }
+            self.input_state.borrow_mut().handle(&event);
+            // Later events are still dispatched, so the state stays in sync.
+            result = result.and(self.input.borrow_mut().notify(event));
         }
+        result
     }
 
+    /// Reads all window events received since the last call, without
+    /// notifying input callbacks.
+    ///
+    /// Notify resize callbacks if the framebuffer changed.
+    pub fn poll_events(&self) -> Result<Vec<WindowEvent>, NotifyError> {
+        let events = self.display.poll_events().collect();
+        try!(self.check_resize());
+        Ok(events)
+    }
 
-    /// Get frame immutable reference.
-    /// # Panics
-    /// Panic if frame not created or something is mutable borrowing the frame.
-    pub fn get_frame(&self) -> Ref<Frame> {
-        unsafe {
-            let cell = self.get_cell();
-            if cell.is_none() {
-                panic!("Frame not exist.")
+    fn translate(&self, event: WindowEvent) -> Option<InputEvent> {
+        let event = match event {
+            WindowEvent::Closed => InputEvent::Close,
+            WindowEvent::ReceivedCharacter(c) => InputEvent::Text(c),
+            WindowEvent::Focused(focused) => {
+                if !focused {
+                    // Releases are not received while unfocused.
+                    self.modifier_keys.set(0);
+                }
+                InputEvent::Focus(focused)
+            }
+            WindowEvent::KeyboardInput(state, scancode, code) => {
+                let key = code.and_then(key);
+                let bit = key.map(modifier_bit).unwrap_or(0);
+                let held = match state {
+                    ElementState::Pressed => self.modifier_keys.get() | bit,
+                    ElementState::Released => self.modifier_keys.get() & !bit,
+                };
+                self.modifier_keys.set(held);
+                let event = KeyEvent {
+                    key: key,
+                    scancode: scancode as u32,
+                    modifiers: modifiers(held),
+                };
+                match state {
+                    ElementState::Pressed => InputEvent::KeyDown(event),
+                    ElementState::Released => InputEvent::KeyUp(event),
+                }
+            }
+            WindowEvent::MouseMoved(x, y) => InputEvent::MouseMove(x, y),
+            WindowEvent::MouseInput(state, button) => {
+                let button = match button {
+                    glutin::MouseButton::Left => MouseButton::Left,
+                    glutin::MouseButton::Right => MouseButton::Right,
+                    glutin::MouseButton::Middle => MouseButton::Middle,
+                    glutin::MouseButton::Other(n) => MouseButton::Other(n),
+                };
+                match state {
+                    ElementState::Pressed => InputEvent::MouseDown(button),
+                    ElementState::Released => InputEvent::MouseUp(button),
+                }
+            }
+            WindowEvent::MouseWheel(delta, _) => {
+                InputEvent::Scroll(match delta {
+                    MouseScrollDelta::LineDelta(x, y) => ScrollDelta::Lines(x, y),
+                    MouseScrollDelta::PixelDelta(x, y) => ScrollDelta::Pixels(x, y),
+                })
             }
-            cell.as_ref().unwrap().borrow()
+            _ => return None,
+        };
+        Some(event)
+    }
+
+    /// Updates the size and notifies resize callbacks if it changed.
+    fn check_resize(&self) -> Result<Option<Resize>, NotifyError> {
+        let dimensions = self.display.get_framebuffer_dimensions();
+        let hidpi_factor = self.display
+            .get_window()
+            .map(|w| w.hidpi_factor())
+            .unwrap_or(1.0);
+        if dimensions == self.dimensions.get() && hidpi_factor == self.hidpi_factor.get() {
+            return Ok(None);
         }
+        let resize = Resize {
+            dimensions: dimensions,
+            hidpi_factor: hidpi_factor,
+        };
+        try!(self.apply_resize(resize));
+        Ok(Some(resize))
     }
 
-    /// Get frame mutable reference.
-    /// # Panics
-    /// Panic if something is borrowing the frame.
-    pub fn get_frame_mut(&self) -> RefMut<Frame> {
-        unsafe {
-            let cell = self.get_cell();
-            if cell.is_none() {
-                panic!("Frame not exist.")
-            }
-            cell.as_ref().unwrap().borrow_mut()
-        }
-    }
-
-    /// End the frame.
-    fn end_frame(&self) -> Result<(), SwapBuffersError> {
-        unsafe {
-            let mut cell = self.get_cell();
-            if cell.is_none() {
-                println!("Frame has already ended.");
-                Ok(())
-            } else {
-                // Test whether the frame is borrowed.
-                // TODO: Waiting `borrow_state` stabilization:
-                // https://github.com/rust-lang/rust/issues/27733
-                let _ = cell.as_ref().unwrap().borrow_mut();
-                let frame = cell.take().unwrap().into_inner();
+    /// Sets the size and notifies resize callbacks.
+    fn apply_resize(&self, resize: Resize) -> Result<(), NotifyError> {
+        self.dimensions.set(resize.dimensions);
+        self.hidpi_factor.set(resize.hidpi_factor);
+        self.resize.borrow_mut().notify(resize)
+    }
+}
+
+
+#[cfg(all(feature = "joystick", target_os = "linux"))]
+fn default_gamepad_backend() -> Option<Box<Backend>> {
+    Some(Box::new(::joystick::JoystickBackend::new()))
+}
+
+
+#[cfg(not(all(feature = "joystick", target_os = "linux")))]
+fn default_gamepad_backend() -> Option<Box<Backend>> {
+    None
+}
+
+
+/// Bit of a modifier key in `Context::modifier_keys`, `0` for other keys.
+fn modifier_bit(key: Key) -> u8 {
+    match key {
+        Key::LShift => 1,
+        Key::RShift => 2,
+        Key::LControl => 4,
+        Key::RControl => 8,
+        Key::LAlt => 16,
+        Key::RAlt => 32,
+        Key::LLogo => 64,
+        Key::RLogo => 128,
+        _ => 0,
+    }
+}
+
+
+fn modifiers(held: u8) -> Modifiers {
+    Modifiers {
+        shift: held & (1 | 2) != 0,
+        ctrl: held & (4 | 8) != 0,
+        alt: held & (16 | 32) != 0,
+        logo: held & (64 | 128) != 0,
+    }
+}
+
+
+fn key(code: VirtualKeyCode) -> Option<Key> {
+    use glium::glutin::VirtualKeyCode as V;
+    let key = match code {
+        V::Key0 => Key::Key0,
+        V::Key1 => Key::Key1,
+        V::Key2 => Key::Key2,
+        V::Key3 => Key::Key3,
+        V::Key4 => Key::Key4,
+        V::Key5 => Key::Key5,
+        V::Key6 => Key::Key6,
+        V::Key7 => Key::Key7,
+        V::Key8 => Key::Key8,
+        V::Key9 => Key::Key9,
+        V::A => Key::A,
+        V::B => Key::B,
+        V::C => Key::C,
+        V::D => Key::D,
+        V::E => Key::E,
+        V::F => Key::F,
+        V::G => Key::G,
+        V::H => Key::H,
+        V::I => Key::I,
+        V::J => Key::J,
+        V::K => Key::K,
+        V::L => Key::L,
+        V::M => Key::M,
+        V::N => Key::N,
+        V::O => Key::O,
+        V::P => Key::P,
+        V::Q => Key::Q,
+        V::R => Key::R,
+        V::S => Key::S,
+        V::T => Key::T,
+        V::U => Key::U,
+        V::V => Key::V,
+        V::W => Key::W,
+        V::X => Key::X,
+        V::Y => Key::Y,
+        V::Z => Key::Z,
+        V::F1 => Key::F1,
+        V::F2 => Key::F2,
+        V::F3 => Key::F3,
+        V::F4 => Key::F4,
+        V::F5 => Key::F5,
+        V::F6 => Key::F6,
+        V::F7 => Key::F7,
+        V::F8 => Key::F8,
+        V::F9 => Key::F9,
+        V::F10 => Key::F10,
+        V::F11 => Key::F11,
+        V::F12 => Key::F12,
+        V::Escape => Key::Escape,
+        V::Tab => Key::Tab,
+        V::Capital => Key::CapsLock,
+        V::Space => Key::Space,
+        V::Return => Key::Enter,
+        V::Back => Key::Backspace,
+        V::Insert => Key::Insert,
+        V::Delete => Key::Delete,
+        V::Home => Key::Home,
+        V::End => Key::End,
+        V::PageUp => Key::PageUp,
+        V::PageDown => Key::PageDown,
+        V::Snapshot => Key::PrintScreen,
+        V::Scroll => Key::ScrollLock,
+        V::Pause => Key::Pause,
+        V::Left => Key::Left,
+        V::Up => Key::Up,
+        V::Right => Key::Right,
+        V::Down => Key::Down,
+        V::LShift => Key::LShift,
+        V::RShift => Key::RShift,
+        V::LControl => Key::LControl,
+        V::RControl => Key::RControl,
+        V::LAlt | V::LMenu => Key::LAlt,
+        V::RAlt | V::RMenu => Key::RAlt,
+        V::LWin => Key::LLogo,
+        V::RWin => Key::RLogo,
+        V::Minus => Key::Minus,
+        V::Equals => Key::Equals,
+        V::LBracket => Key::LBracket,
+        V::RBracket => Key::RBracket,
+        V::Backslash => Key::Backslash,
+        V::Semicolon => Key::Semicolon,
+        V::Apostrophe => Key::Apostrophe,
+        V::Grave => Key::Grave,
+        V::Comma => Key::Comma,
+        V::Period => Key::Period,
+        V::Slash => Key::Slash,
+        V::Numlock => Key::NumLock,
+        V::Numpad0 => Key::Numpad0,
+        V::Numpad1 => Key::Numpad1,
+        V::Numpad2 => Key::Numpad2,
+        V::Numpad3 => Key::Numpad3,
+        V::Numpad4 => Key::Numpad4,
+        V::Numpad5 => Key::Numpad5,
+        V::Numpad6 => Key::Numpad6,
+        V::Numpad7 => Key::Numpad7,
+        V::Numpad8 => Key::Numpad8,
+        V::Numpad9 => Key::Numpad9,
+        V::Add => Key::NumpadAdd,
+        V::Subtract => Key::NumpadSubtract,
+        V::Multiply => Key::NumpadMultiply,
+        V::Divide => Key::NumpadDivide,
+        V::Decimal => Key::NumpadDecimal,
+        V::NumpadEnter => Key::NumpadEnter,
+        _ => return None,
+    };
+    Some(key)
+}
+
+
+
+/// Framebuffer size change event.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Resize {
+    /// New framebuffer size in pixels.
+    pub dimensions: (u32, u32),
+    /// New HiDPI factor.
+    pub hidpi_factor: f32,
+}
+
+
+/// The current frame of a context.
+///
+/// Derefs to `Frame`, so any `Surface` method can be used for drawing.
+/// Buffers are swapped when the guard is dropped, use `finish` to get
+/// the swap result.
+pub struct FrameGuard<'a> {
+    ctx: &'a Context,
+    frame: Option<Frame>,
+}
+
+
+impl<'a> FrameGuard<'a> {
+    /// Ends the frame and swaps buffers.
+    pub fn finish(mut self) -> Result<(), FrameError> {
+        self.end().map_err(FrameError::from)
+    }
+
+    fn end(&mut self) -> Result<(), SwapBuffersError> {
+        match self.frame.take() {
+            Some(frame) => {
+                self.ctx.in_frame.set(false);
                 frame.finish()
             }
+            None => Ok(()),
         }
     }
+}
 
-    /// Start a new frame and auto end it.
-    pub fn frame<F>(&self, f: F) -> Result<(), SwapBuffersError>
-        where F: FnOnce()
-    {
-        // TODO: Refactor and error handling.
-        self.start_frame();
-        f();
-        self.end_frame()
+
+impl<'a> Deref for FrameGuard<'a> {
+    type Target = Frame;
+
+    fn deref(&self) -> &Frame {
+        self.frame.as_ref().unwrap()
     }
+}
 
-    /// Returns the ratio between the backing framebuffer resolution
-    /// and the window size in screen pixels.
-    pub fn hidpi_factor(&self) -> f32 {
-        self.display.get_window().unwrap().hidpi_factor()
+
+impl<'a> DerefMut for FrameGuard<'a> {
+    fn deref_mut(&mut self) -> &mut Frame {
+        self.frame.as_mut().unwrap()
+    }
+}
+
+
+impl<'a> Drop for FrameGuard<'a> {
+    fn drop(&mut self) {
+        let _ = self.end();
+    }
+}
+
+
+/// Errors which can occur when using frames.
+#[derive(Debug)]
+pub enum FrameError {
+    /// A frame has already started.
+    Nested,
+    /// Buffers swap failure.
+    SwapBuffers(SwapBuffersError),
+}
+
+
+impl From<SwapBuffersError> for FrameError {
+    fn from(err: SwapBuffersError) -> FrameError {
+        FrameError::SwapBuffers(err)
+    }
+}
+
+/// Which monitor a fullscreen window uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Monitor {
+    /// The primary monitor.
+    Primary,
+    /// Monitor at the index of `available_monitors()`.
+    Index(usize),
+}
+
+
+/// Names of the connected monitors.
+pub fn available_monitors() -> Vec<String> {
+    glutin::get_available_monitors()
+        .map(|monitor| monitor.get_name().unwrap_or_else(String::new))
+        .collect()
+}
+
+
+/// Context settings.
+///
+/// # Example
+///
+/// ```no_run
+/// use rier::context::ContextBuilder;
+///
+/// let ctx = ContextBuilder::new()
+///     .title("Game")
+///     .dimensions((1280, 720))
+///     .multisampling(4)
+///     .resizable(false)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ContextBuilder {
+    title: String,
+    dimensions: (u32, u32),
+    depth_bits: Option<u8>,
+    stencil_bits: Option<u8>,
+    multisampling: Option<u16>,
+    vsync: bool,
+    srgb: Option<bool>,
+    gl_version: Option<(u8, u8)>,
+    gl_profile: Option<GlProfile>,
+    fullscreen: Option<Monitor>,
+    resizable: bool,
+    decorations: bool,
+    position: Option<(i32, i32)>,
+}
+
+
+impl ContextBuilder {
+    /// Creates default settings: 800x600, 24-bit depth buffer and vsync.
+    pub fn new() -> ContextBuilder {
+        ContextBuilder {
+            title: String::from("rier"),
+            dimensions: (800, 600),
+            depth_bits: Some(24),
+            stencil_bits: None,
+            multisampling: None,
+            vsync: true,
+            srgb: None,
+            gl_version: None,
+            gl_profile: None,
+            fullscreen: None,
+            resizable: true,
+            decorations: true,
+            position: None,
+        }
+    }
+
+    /// Sets window title.
+    pub fn title<T: ToString>(self, title: T) -> ContextBuilder {
+        ContextBuilder { title: title.to_string(), ..self }
+    }
+
+    /// Sets window or offscreen buffer size.
+    pub fn dimensions(self, dimensions: (u32, u32)) -> ContextBuilder {
+        ContextBuilder { dimensions: dimensions, ..self }
+    }
+
+    /// Sets depth buffer bits, `None` for no depth buffer.
+    pub fn depth_bits(self, bits: Option<u8>) -> ContextBuilder {
+        ContextBuilder { depth_bits: bits, ..self }
+    }
+
+    /// Sets stencil buffer bits, `None` for no stencil buffer.
+    pub fn stencil_bits(self, bits: Option<u8>) -> ContextBuilder {
+        ContextBuilder { stencil_bits: bits, ..self }
+    }
+
+    /// Sets multisampling sample count of the window, must be a power of two.
+    ///
+    /// Offscreen targets set their own count, see `RenderTarget::with_samples`.
+    pub fn multisampling(self, samples: u16) -> ContextBuilder {
+        ContextBuilder { multisampling: Some(samples), ..self }
+    }
+
+    /// Enables or disables vsync.
+    pub fn vsync(self, vsync: bool) -> ContextBuilder {
+        ContextBuilder { vsync: vsync, ..self }
+    }
+
+    /// Requests an sRGB framebuffer.
+    pub fn srgb(self, srgb: bool) -> ContextBuilder {
+        ContextBuilder { srgb: Some(srgb), ..self }
+    }
+
+    /// Requests a specific OpenGL version.
+    pub fn gl_version(self, major: u8, minor: u8) -> ContextBuilder {
+        ContextBuilder { gl_version: Some((major, minor)), ..self }
+    }
+
+    /// Requests an OpenGL profile.
+    pub fn gl_profile(self, profile: GlProfile) -> ContextBuilder {
+        ContextBuilder { gl_profile: Some(profile), ..self }
+    }
+
+    /// Makes the window fullscreen on the monitor.
+    pub fn fullscreen(self, monitor: Monitor) -> ContextBuilder {
+        ContextBuilder { fullscreen: Some(monitor), ..self }
+    }
+
+    /// Whether the user can resize the window, default `true`.
+    pub fn resizable(self, resizable: bool) -> ContextBuilder {
+        ContextBuilder { resizable: resizable, ..self }
+    }
+
+    /// Whether the window has borders and title bar, default `true`.
+    pub fn decorations(self, decorations: bool) -> ContextBuilder {
+        ContextBuilder { decorations: decorations, ..self }
+    }
+
+    /// Sets initial window position in screen pixels.
+    pub fn position(self, x: i32, y: i32) -> ContextBuilder {
+        ContextBuilder { position: Some((x, y)), ..self }
+    }
+
+    fn gl_request(&self) -> GlRequest {
+        match self.gl_version {
+            Some(version) => GlRequest::Specific(Api::OpenGl, version),
+            None => GlRequest::Latest,
+        }
+    }
+
+    /// Builds OpenGL context and create a window.
+    pub fn build(self) -> Result<Context, ContextError> {
+        let (width, height) = self.dimensions;
+        let mut builder = WindowBuilder::new()
+            .with_title(self.title.clone())
+            .with_dimensions(width, height)
+            .with_gl(self.gl_request())
+            .with_srgb(self.srgb)
+            .with_decorations(self.decorations);
+        if let Some(bits) = self.depth_bits {
+            builder = builder.with_depth_buffer(bits);
+        }
+        if let Some(bits) = self.stencil_bits {
+            builder = builder.with_stencil_buffer(bits);
+        }
+        if let Some(samples) = self.multisampling {
+            builder = builder.with_multisampling(samples);
+        }
+        if self.vsync {
+            builder = builder.with_vsync();
+        }
+        if let Some(profile) = self.gl_profile {
+            builder = builder.with_gl_profile(profile);
+        }
+        if !self.resizable {
+            builder = builder.with_min_dimensions(width, height)
+                .with_max_dimensions(width, height);
+        }
+        builder = match self.fullscreen {
+            None => builder,
+            Some(Monitor::Primary) => builder.with_fullscreen(glutin::get_primary_monitor()),
+            Some(Monitor::Index(i)) => {
+                match glutin::get_available_monitors().nth(i) {
+                    Some(monitor) => builder.with_fullscreen(monitor),
+                    None => return Err(ContextError::MonitorNotFound(i)),
+                }
+            }
+        };
+        let display = try!(builder.build_glium());
+        if let (Some((x, y)), Some(window)) = (self.position, display.get_window()) {
+            window.set_position(x, y);
+        }
+        Ok(Context::new(display))
+    }
+
+    /// Builds OpenGL context without a window.
+    ///
+    /// Only the dimensions and OpenGL version settings are used.
+    pub fn build_headless(self) -> Result<Context, ContextError> {
+        let (width, height) = self.dimensions;
+        let mut builder = HeadlessRendererBuilder::new(width, height).with_gl(self.gl_request());
+        if let Some(profile) = self.gl_profile {
+            builder = builder.with_gl_profile(profile);
+        }
+        let display = try!(builder.build_glium());
+        Ok(Context::new(display))
+    }
+}
+
+
+impl Default for ContextBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+/// Errors which can occur when attempting to create a context.
+#[derive(Debug)]
+pub enum ContextError {
+    /// Window or OpenGL context create failure.
+    Creation(GliumCreationError<CreationError>),
+    /// No monitor at the fullscreen index.
+    MonitorNotFound(usize),
+}
+
+
+impl From<GliumCreationError<CreationError>> for ContextError {
+    fn from(err: GliumCreationError<CreationError>) -> ContextError {
+        ContextError::Creation(err)
     }
 }
@@ -9,8 +9,48 @@
 //!
 //! let mut notifier = Notifier::<i32>::new();
 //! notifier.register(|e| { assert_eq!(e, &42); Return::Next });
-//! notifier.notify(42);
+//! notifier.notify(42).unwrap();
 //! ```
+//!
+//! # Spawned events
+//!
+//! A callback can return `Return::Spwan` to notify a new event. Spawned
+//! events are queued and dispatched breadth-first: every callback sees the
+//! current event before any event spawned from it, and spawned events are
+//! dispatched in the order they were spawned. Callbacks removed while
+//! dispatching an event do not see the events spawned from it.
+//!
+//! # Subscriptions
+//!
+//! `register` returns a `Subscription` that removes the callback from
+//! outside, without borrowing the notifier.
+//!
+//! ```
+//! use rier::event::{Notifier, Return};
+//!
+//! let mut notifier = Notifier::<i32>::new();
+//! let subscription = notifier.register(|_| Return::Next);
+//! {
+//!     // Removed when `_scoped` goes out of scope.
+//!     let _scoped = notifier.register(|_| Return::Next).scoped();
+//!     assert_eq!(notifier.len(), 2);
+//! }
+//! assert_eq!(notifier.len(), 1);
+//! subscription.unsubscribe();
+//! assert!(notifier.is_empty());
+//! ```
+//!
+//! # Deferred events
+//!
+//! An `EventQueue` buffers events and notifies them later, when it is
+//! flushed. An `EventBus` keeps a notifier and a queue for each event type,
+//! the main loop flushes the bus of `Time::events` after each update.
+use std::rc::Rc;
+use std::mem;
+use std::any::{Any, TypeId};
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+
 
 /// Callback function returns.
 pub enum Return<E> {
@@ -20,34 +60,548 @@ pub enum Return<E> {
     Dead,
     /// Start notify this new event.
     Spwan(Box<E>),
+    /// The event is handled, the remaining callbacks do not see it.
+    Consumed,
+}
+
+/// Handle of a registered callback.
+///
+/// Dropping it keeps the callback registered, unless it is `scoped`.
+#[derive(Debug)]
+pub struct Subscription {
+    alive: Rc<Cell<bool>>,
+    scoped: bool,
+}
+
+
+impl Subscription {
+    /// Removes the callback when the subscription is dropped.
+    pub fn scoped(self) -> Subscription {
+        Subscription {
+            alive: self.alive.clone(),
+            scoped: true,
+        }
+    }
+
+    /// Removes the callback, it will not be called again.
+    pub fn unsubscribe(&self) {
+        self.alive.set(false);
+    }
+
+    /// Returns `true` if the callback is still registered.
+    pub fn is_active(&self) -> bool {
+        self.alive.get()
+    }
+}
+
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if self.scoped {
+            self.alive.set(false);
+        }
+    }
+}
+
+
+/// Subscriber object that keeps its own state.
+///
+/// # Example
+///
+/// ```
+/// use std::rc::Rc;
+/// use std::cell::RefCell;
+/// use rier::event::{Notifier, Listener, Return};
+///
+/// struct Counter(usize);
+///
+/// impl Listener<i32> for Counter {
+///     fn on_event(&mut self, _: &i32) -> Return<i32> {
+///         self.0 += 1;
+///         Return::Next
+///     }
+/// }
+///
+/// let counter = Rc::new(RefCell::new(Counter(0)));
+/// let mut notifier = Notifier::new();
+/// notifier.listen(counter.clone());
+/// notifier.notify(1).unwrap();
+/// notifier.notify(2).unwrap();
+/// assert_eq!(counter.borrow().0, 2);
+/// ```
+pub trait Listener<E> {
+    fn on_event(&mut self, event: &E) -> Return<E>;
+}
+
+
+/// Shared listener, the other owners can read its state.
+///
+/// The listener must not be borrowed while it is notified.
+impl<E, L: Listener<E>> Listener<E> for Rc<RefCell<L>> {
+    fn on_event(&mut self, event: &E) -> Return<E> {
+        self.borrow_mut().on_event(event)
+    }
 }
 
+
+/// Closure adapter.
+struct Callback<F>(F);
+
+
+impl<E, F> Listener<E> for Callback<F>
+    where F: FnMut(&E) -> Return<E>
+{
+    fn on_event(&mut self, event: &E) -> Return<E> {
+        (self.0)(event)
+    }
+}
+
+
+struct Subscriber<E> {
+    alive: Rc<Cell<bool>>,
+    priority: i32,
+    listener: Box<Listener<E>>,
+}
+
+
 /// Event sender.
 pub struct Notifier<E> {
-    subscribers: Vec<Box<Fn(&E) -> Return<E>>>,
+    subscribers: Vec<Subscriber<E>>,
+    max_depth: usize,
+    max_events: usize,
 }
 
 
 impl<E> Notifier<E> {
     pub fn new() -> Notifier<E> {
-        Notifier { subscribers: Vec::new() }
+        Notifier {
+            subscribers: Vec::new(),
+            max_depth: 16,
+            max_events: 1024,
+        }
+    }
+
+    /// Sets how many generations of spawned events are allowed, default `16`.
+    ///
+    /// The notified event is depth `0`, events it spawns are depth `1`.
+    pub fn max_depth(self, depth: usize) -> Notifier<E> {
+        Notifier { max_depth: depth, ..self }
+    }
+
+    /// Sets how many events one `notify` may dispatch, including spawned
+    /// ones, default `1024`.
+    pub fn max_events(self, n: usize) -> Notifier<E> {
+        Notifier { max_events: n, ..self }
     }
 
     /// Register event callback function.
-    pub fn register<F>(&mut self, callback: F)
-        where F: 'static + Fn(&E) -> Return<E>
+    ///
+    /// The callback may change the state it captures.
+    ///
+    /// ```
+    /// use rier::event::{Notifier, Return};
+    ///
+    /// let mut notifier = Notifier::new();
+    /// let mut buffer = Vec::new();
+    /// notifier.register(move |&e: &char| {
+    ///     buffer.push(e);
+    ///     match e {
+    ///         '\n' => Return::Dead,
+    ///         _ => Return::Next,
+    ///     }
+    /// });
+    /// notifier.notify('a').unwrap();
+    /// notifier.notify('\n').unwrap();
+    /// assert!(notifier.is_empty());
+    /// ```
+    pub fn register<F>(&mut self, callback: F) -> Subscription
+        where F: 'static + FnMut(&E) -> Return<E>
+    {
+        self.listen_with_priority(0, Callback(callback))
+    }
+
+    /// Register event callback function with a priority.
+    ///
+    /// Callbacks of higher priority are called first, callbacks of the
+    /// same priority in registration order. `register` uses priority `0`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rier::event::{Notifier, Return};
+    ///
+    /// let mut notifier = Notifier::<(i32, i32)>::new();
+    /// notifier.register(|_| panic!("gameplay sees a click on the UI"));
+    /// notifier.register_with_priority(100, |&(x, y)| {
+    ///     // Clicks on the button are handled by the UI.
+    ///     if x < 100 && y < 20 { Return::Consumed } else { Return::Next }
+    /// });
+    /// notifier.notify((10, 10)).unwrap();
+    /// ```
+    pub fn register_with_priority<F>(&mut self, priority: i32, callback: F) -> Subscription
+        where F: 'static + FnMut(&E) -> Return<E>
     {
-        self.subscribers.push(Box::new(callback));
+        self.listen_with_priority(priority, Callback(callback))
+    }
+
+    /// Register a listener object.
+    ///
+    /// Pass a `Rc<RefCell<L>>` to keep access to its state.
+    pub fn listen<L>(&mut self, listener: L) -> Subscription
+        where L: 'static + Listener<E>
+    {
+        self.listen_with_priority(0, listener)
+    }
+
+    /// Register a listener object with a priority.
+    pub fn listen_with_priority<L>(&mut self, priority: i32, listener: L) -> Subscription
+        where L: 'static + Listener<E>
+    {
+        let alive = Rc::new(Cell::new(true));
+        let index = self.subscribers
+            .iter()
+            .position(|s| s.priority < priority)
+            .unwrap_or(self.subscribers.len());
+        self.subscribers.insert(index,
+                                Subscriber {
+                                    alive: alive.clone(),
+                                    priority: priority,
+                                    listener: Box::new(listener),
+                                });
+        Subscription {
+            alive: alive,
+            scoped: false,
+        }
+    }
+
+    /// Number of registered callbacks.
+    pub fn len(&self) -> usize {
+        self.subscribers.iter().filter(|s| s.alive.get()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all callbacks.
+    pub fn clear(&mut self) {
+        for subscriber in self.subscribers.drain(..) {
+            subscriber.alive.set(false);
+        }
+    }
+
+    /// Notify new event, and the events spawned by the callbacks.
+    ///
+    /// If the limits are exceeded the remaining spawned events are dropped
+    /// and an error is returned. Callbacks removed before that stay removed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use std::cell::RefCell;
+    /// use rier::event::{Notifier, Return, NotifyError};
+    ///
+    /// let log = Rc::new(RefCell::new(Vec::new()));
+    /// let mut notifier = Notifier::<u32>::new();
+    ///
+    /// let seen = log.clone();
+    /// notifier.register(move |&e| {
+    ///     seen.borrow_mut().push(("a", e));
+    ///     match e {
+    ///         1 => Return::Spwan(Box::new(2)),
+    ///         _ => Return::Next,
+    ///     }
+    /// });
+    /// let seen = log.clone();
+    /// notifier.register(move |&e| {
+    ///     // Removed while `a` is spawning, never sees `2`.
+    ///     seen.borrow_mut().push(("b", e));
+    ///     Return::Dead
+    /// });
+    /// let seen = log.clone();
+    /// notifier.register(move |&e| {
+    ///     seen.borrow_mut().push(("c", e));
+    ///     match e {
+    ///         1 => Return::Spwan(Box::new(3)),
+    ///         _ => Return::Next,
+    ///     }
+    /// });
+    ///
+    /// notifier.notify(1).unwrap();
+    /// assert_eq!(*log.borrow(),
+    ///            [("a", 1), ("b", 1), ("c", 1), ("a", 2), ("c", 2), ("a", 3), ("c", 3)]);
+    ///
+    /// // A callback that always spawns is stopped.
+    /// let mut notifier = Notifier::<u32>::new().max_depth(8);
+    /// notifier.register(|&e| Return::Spwan(Box::new(e + 1)));
+    /// match notifier.notify(0) {
+    ///     Err(NotifyError::Depth(8)) => (),
+    ///     _ => panic!(),
+    /// }
+    /// ```
+    pub fn notify(&mut self, event: E) -> Result<(), NotifyError> {
+        let mut queue = VecDeque::new();
+        queue.push_back((event, 0));
+        let mut dispatched = 0;
+        while let Some((event, depth)) = queue.pop_front() {
+            if depth > self.max_depth {
+                return Err(NotifyError::Depth(self.max_depth));
+            }
+            if dispatched == self.max_events {
+                return Err(NotifyError::Budget(self.max_events));
+            }
+            dispatched += 1;
+            let subscribers = mem::replace(&mut self.subscribers, Vec::new());
+            let mut consumed = false;
+            for mut s in subscribers {
+                if !s.alive.get() {
+                    continue;
+                }
+                if !consumed {
+                    match s.listener.on_event(&event) {
+                        Return::Dead => {
+                            s.alive.set(false);
+                            continue;
+                        }
+                        Return::Next => (),
+                        Return::Spwan(spawned) => queue.push_back((*spawned, depth + 1)),
+                        Return::Consumed => consumed = true,
+                    }
+                }
+                self.subscribers.push(s);
+            }
+        }
+        Ok(())
     }
+}
+
+
+impl<E> Default for Notifier<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+/// Errors which can occur when notifying an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyError {
+    /// Spawned events nested deeper than the maximum depth.
+    Depth(usize),
+    /// More events dispatched than the maximum count.
+    Budget(usize),
+}
+
+
+/// Events buffered until flushed.
+///
+/// Clones share the same buffer, so callbacks can keep one to emit events
+/// while the queue is being flushed. Those events wait for the next flush.
+///
+/// # Example
+///
+/// ```
+/// use rier::event::{Notifier, EventQueue, Return};
+///
+/// let mut notifier = Notifier::new();
+/// let queue = EventQueue::new();
+/// let emitter = queue.clone();
+/// notifier.register(move |&e: &u32| {
+///     if e < 3 {
+///         emitter.push(e + 1);
+///     }
+///     Return::Next
+/// });
+///
+/// queue.push(0);
+/// queue.flush(&mut notifier).unwrap();
+/// assert_eq!(queue.len(), 1);
+/// ```
+pub struct EventQueue<E> {
+    events: Rc<RefCell<VecDeque<E>>>,
+}
+
+
+impl<E> EventQueue<E> {
+    pub fn new() -> EventQueue<E> {
+        EventQueue { events: Rc::new(RefCell::new(VecDeque::new())) }
+    }
+
+    /// Buffers an event.
+    pub fn push(&self, event: E) {
+        self.events.borrow_mut().push_back(event);
+    }
+
+    /// Number of buffered events.
+    pub fn len(&self) -> usize {
+        self.events.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.borrow().is_empty()
+    }
+
+    /// Drops all buffered events.
+    pub fn clear(&self) {
+        self.events.borrow_mut().clear();
+    }
+
+    /// Notifies the buffered events in the order they were pushed.
+    ///
+    /// On error the events not notified yet stay buffered.
+    pub fn flush(&self, notifier: &mut Notifier<E>) -> Result<(), NotifyError> {
+        let mut events = mem::replace(&mut *self.events.borrow_mut(), VecDeque::new());
+        while let Some(event) = events.pop_front() {
+            if let Err(err) = notifier.notify(event) {
+                let mut buffer = self.events.borrow_mut();
+                events.extend(buffer.drain(..));
+                *buffer = events;
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+}
+
+
+impl<E> Clone for EventQueue<E> {
+    fn clone(&self) -> Self {
+        EventQueue { events: self.events.clone() }
+    }
+}
+
+
+impl<E> Default for EventQueue<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-    /// Notify new event.
-    pub fn notify(&mut self, event: E) {
-        self.subscribers.retain(|f| {
-            match f(&event) {
-                Return::Dead => false,
-                Return::Next => true,
-                _ => unimplemented!(),
+
+/// Notifier and queue of one event type.
+struct Channel<E> {
+    notifier: Notifier<E>,
+    queue: EventQueue<E>,
+}
+
+
+trait AnyChannel {
+    fn flush(&mut self) -> Result<(), NotifyError>;
+    fn pending(&self) -> usize;
+    fn as_any(&mut self) -> &mut Any;
+}
+
+
+impl<E: 'static> AnyChannel for Channel<E> {
+    fn flush(&mut self) -> Result<(), NotifyError> {
+        self.queue.flush(&mut self.notifier)
+    }
+
+    fn pending(&self) -> usize {
+        self.queue.len()
+    }
+
+    fn as_any(&mut self) -> &mut Any {
+        self
+    }
+}
+
+
+/// Deferred events of any type, keyed by the event type.
+///
+/// # Example
+///
+/// ```
+/// use std::rc::Rc;
+/// use std::cell::Cell;
+/// use rier::event::{EventBus, Return};
+///
+/// struct Score(u32);
+/// struct GameOver;
+///
+/// let total = Rc::new(Cell::new(0));
+/// let over = Rc::new(Cell::new(false));
+/// let mut bus = EventBus::new();
+/// let t = total.clone();
+/// bus.notifier::<Score>().register(move |score| {
+///     t.set(t.get() + score.0);
+///     Return::Next
+/// });
+/// let o = over.clone();
+/// bus.notifier::<GameOver>().register(move |_| {
+///     o.set(true);
+///     Return::Next
+/// });
+///
+/// bus.emit(Score(10));
+/// bus.emit(GameOver);
+/// assert_eq!(bus.pending(), 2);
+/// assert_eq!(total.get(), 0);
+/// bus.flush().unwrap();
+/// assert_eq!(bus.pending(), 0);
+/// assert_eq!(total.get(), 10);
+/// assert!(over.get());
+/// ```
+pub struct EventBus {
+    channels: Vec<(TypeId, Box<AnyChannel>)>,
+}
+
+
+impl EventBus {
+    pub fn new() -> EventBus {
+        EventBus { channels: Vec::new() }
+    }
+
+    fn channel<E: 'static>(&mut self) -> &mut Channel<E> {
+        let id = TypeId::of::<E>();
+        let index = match self.channels.iter().position(|&(ref t, _)| *t == id) {
+            Some(index) => index,
+            None => {
+                let channel = Channel::<E> {
+                    notifier: Notifier::new(),
+                    queue: EventQueue::new(),
+                };
+                self.channels.push((id, Box::new(channel)));
+                self.channels.len() - 1
             }
-        })
+        };
+        self.channels[index].1.as_any().downcast_mut().unwrap()
+    }
+
+    /// The notifier of an event type, to register callbacks.
+    pub fn notifier<E: 'static>(&mut self) -> &mut Notifier<E> {
+        &mut self.channel().notifier
+    }
+
+    /// The queue of an event type, callbacks can keep it to emit events.
+    pub fn queue<E: 'static>(&mut self) -> EventQueue<E> {
+        self.channel().queue.clone()
+    }
+
+    /// Buffers an event until the next flush.
+    pub fn emit<E: 'static>(&mut self, event: E) {
+        self.channel().queue.push(event);
+    }
+
+    /// Number of buffered events of all types.
+    pub fn pending(&self) -> usize {
+        self.channels.iter().map(|&(_, ref channel)| channel.pending()).sum()
+    }
+
+    /// Notifies the buffered events, event types in the order they were
+    /// first used.
+    pub fn flush(&mut self) -> Result<(), NotifyError> {
+        for &mut (_, ref mut channel) in &mut self.channels {
+            try!(channel.flush());
+        }
+        Ok(())
+    }
+}
+
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
     }
 }
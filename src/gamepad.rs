@@ -0,0 +1,351 @@
+//! Gamepads.
+//!
+//! Gamepad events come from a `Backend` set by
+//! `Context::set_gamepad_backend`. They are notified as
+//! `InputEvent::Gamepad` by `Context::pump_events`, and the state of each
+//! connected gamepad is kept by `Input`.
+//!
+//! Windowed contexts read the joystick devices on Linux, see the
+//! `joystick` module. `VirtualBackend` injects events from code, for tests.
+//!
+//! # Example
+//!
+//! ```
+//! use rier::input::{Input, InputEvent};
+//! use rier::gamepad::{Backend, VirtualBackend, Button, Axis};
+//!
+//! let mut backend = VirtualBackend::new();
+//! let pad = backend.connect("Test pad");
+//! backend.press(pad, Button::South);
+//! backend.set_axis(pad, Axis::LeftX, -0.5);
+//!
+//! let mut input = Input::new();
+//! input.begin_frame();
+//! for event in backend.poll() {
+//!     input.handle(&InputEvent::Gamepad(event));
+//! }
+//! assert_eq!(input.gamepads(), vec![pad]);
+//! let state = input.gamepad(pad).unwrap();
+//! assert_eq!(state.name(), "Test pad");
+//! assert!(state.is_pressed(Button::South));
+//! assert_eq!(state.axis(Axis::LeftX), -0.5);
+//!
+//! // Held buttons are released on disconnection.
+//! backend.disconnect(pad);
+//! input.begin_frame();
+//! for event in backend.poll() {
+//!     input.handle(&InputEvent::Gamepad(event));
+//! }
+//! assert!(input.gamepad(pad).unwrap().is_released(Button::South));
+//! input.begin_frame();
+//! assert!(input.gamepads().is_empty());
+//! ```
+use std::fmt;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashSet, HashMap, VecDeque};
+
+
+/// Identifies a gamepad while it is connected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GamepadId(pub usize);
+
+
+/// Gamepad buttons, by position on a standard layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Button {
+    /// Bottom face button, A on Xbox.
+    South,
+    /// Right face button, B on Xbox.
+    East,
+    /// Left face button, X on Xbox.
+    West,
+    /// Top face button, Y on Xbox.
+    North,
+    LeftBumper,
+    RightBumper,
+    Select,
+    Start,
+    /// Center logo button.
+    Mode,
+    /// Left stick pressed.
+    LeftStick,
+    /// Right stick pressed.
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+
+/// Gamepad axes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Axis {
+    /// Left stick, `[-1, 1]`, right is positive.
+    LeftX,
+    /// Left stick, `[-1, 1]`, up is positive.
+    LeftY,
+    RightX,
+    RightY,
+    /// Trigger, `[0, 1]`.
+    LeftTrigger,
+    RightTrigger,
+}
+
+
+impl Button {
+    /// Name of the button, the variant name, e.g. `"South"`.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Button::South => "South",
+            Button::East => "East",
+            Button::West => "West",
+            Button::North => "North",
+            Button::LeftBumper => "LeftBumper",
+            Button::RightBumper => "RightBumper",
+            Button::Select => "Select",
+            Button::Start => "Start",
+            Button::Mode => "Mode",
+            Button::LeftStick => "LeftStick",
+            Button::RightStick => "RightStick",
+            Button::DPadUp => "DPadUp",
+            Button::DPadDown => "DPadDown",
+            Button::DPadLeft => "DPadLeft",
+            Button::DPadRight => "DPadRight",
+        }
+    }
+}
+
+
+impl fmt::Display for Button {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+
+impl FromStr for Button {
+    type Err = ();
+
+    /// Parses the name returned by `name`.
+    fn from_str(s: &str) -> Result<Button, ()> {
+        let button = match s {
+            "South" => Button::South,
+            "East" => Button::East,
+            "West" => Button::West,
+            "North" => Button::North,
+            "LeftBumper" => Button::LeftBumper,
+            "RightBumper" => Button::RightBumper,
+            "Select" => Button::Select,
+            "Start" => Button::Start,
+            "Mode" => Button::Mode,
+            "LeftStick" => Button::LeftStick,
+            "RightStick" => Button::RightStick,
+            "DPadUp" => Button::DPadUp,
+            "DPadDown" => Button::DPadDown,
+            "DPadLeft" => Button::DPadLeft,
+            "DPadRight" => Button::DPadRight,
+            _ => return Err(()),
+        };
+        Ok(button)
+    }
+}
+
+
+impl Axis {
+    /// Name of the axis, the variant name, e.g. `"LeftX"`.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Axis::LeftX => "LeftX",
+            Axis::LeftY => "LeftY",
+            Axis::RightX => "RightX",
+            Axis::RightY => "RightY",
+            Axis::LeftTrigger => "LeftTrigger",
+            Axis::RightTrigger => "RightTrigger",
+        }
+    }
+}
+
+
+impl fmt::Display for Axis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+
+impl FromStr for Axis {
+    type Err = ();
+
+    /// Parses the name returned by `name`.
+    fn from_str(s: &str) -> Result<Axis, ()> {
+        let axis = match s {
+            "LeftX" => Axis::LeftX,
+            "LeftY" => Axis::LeftY,
+            "RightX" => Axis::RightX,
+            "RightY" => Axis::RightY,
+            "LeftTrigger" => Axis::LeftTrigger,
+            "RightTrigger" => Axis::RightTrigger,
+            _ => return Err(()),
+        };
+        Ok(axis)
+    }
+}
+
+
+/// Gamepad event.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GamepadEvent {
+    /// Gamepad found, with its name.
+    Connected(GamepadId, String),
+    Disconnected(GamepadId),
+    ButtonDown(GamepadId, Button),
+    ButtonUp(GamepadId, Button),
+    AxisMoved(GamepadId, Axis, f32),
+}
+
+
+/// Source of gamepad events.
+pub trait Backend {
+    /// Returns the events since the last call.
+    ///
+    /// Gamepads connected before the first call are reported as
+    /// `Connected` events.
+    fn poll(&mut self) -> Vec<GamepadEvent>;
+}
+
+
+/// Backend driven by code instead of hardware.
+///
+/// Clones share the same gamepads, so a test can keep one after giving
+/// the other to the context.
+#[derive(Clone, Default)]
+pub struct VirtualBackend {
+    events: Rc<RefCell<VecDeque<GamepadEvent>>>,
+    next_id: Rc<Cell<usize>>,
+}
+
+
+impl VirtualBackend {
+    pub fn new() -> VirtualBackend {
+        VirtualBackend::default()
+    }
+
+    /// Connects a new gamepad.
+    pub fn connect(&self, name: &str) -> GamepadId {
+        let id = GamepadId(self.next_id.get());
+        self.next_id.set(id.0 + 1);
+        self.push(GamepadEvent::Connected(id, name.to_string()));
+        id
+    }
+
+    pub fn disconnect(&self, id: GamepadId) {
+        self.push(GamepadEvent::Disconnected(id));
+    }
+
+    pub fn press(&self, id: GamepadId, button: Button) {
+        self.push(GamepadEvent::ButtonDown(id, button));
+    }
+
+    pub fn release(&self, id: GamepadId, button: Button) {
+        self.push(GamepadEvent::ButtonUp(id, button));
+    }
+
+    pub fn set_axis(&self, id: GamepadId, axis: Axis, value: f32) {
+        self.push(GamepadEvent::AxisMoved(id, axis, value));
+    }
+
+    /// Queues any event.
+    pub fn push(&self, event: GamepadEvent) {
+        self.events.borrow_mut().push_back(event);
+    }
+}
+
+
+impl Backend for VirtualBackend {
+    fn poll(&mut self) -> Vec<GamepadEvent> {
+        self.events.borrow_mut().drain(..).collect()
+    }
+}
+
+
+/// Button and axis state of a connected gamepad.
+#[derive(Debug, Clone, Default)]
+pub struct GamepadState {
+    name: String,
+    held: HashSet<Button>,
+    pressed: HashSet<Button>,
+    released: HashSet<Button>,
+    axes: HashMap<Axis, f32>,
+    disconnected: bool,
+}
+
+
+impl GamepadState {
+    pub fn new(name: &str) -> GamepadState {
+        GamepadState { name: name.to_string(), ..GamepadState::default() }
+    }
+
+    /// Clears the per-frame state.
+    pub fn begin_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+    }
+
+    /// Updates the state by an event of this gamepad.
+    pub fn handle(&mut self, event: &GamepadEvent) {
+        match *event {
+            GamepadEvent::ButtonDown(_, button) => {
+                if self.held.insert(button) {
+                    self.pressed.insert(button);
+                }
+            }
+            GamepadEvent::ButtonUp(_, button) => {
+                if self.held.remove(&button) {
+                    self.released.insert(button);
+                }
+            }
+            GamepadEvent::AxisMoved(_, axis, value) => {
+                self.axes.insert(axis, value);
+            }
+            GamepadEvent::Disconnected(_) => {
+                // Held buttons read as released for the rest of the frame.
+                self.released.extend(self.held.drain());
+                self.disconnected = true;
+            }
+            _ => (),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns `false` after `Disconnected`, until the state is dropped at
+    /// the next frame.
+    pub fn is_connected(&self) -> bool {
+        !self.disconnected
+    }
+
+    /// Returns `true` if the button is down.
+    pub fn is_held(&self, button: Button) -> bool {
+        self.held.contains(&button)
+    }
+
+    /// Returns `true` if the button went down this frame.
+    pub fn is_pressed(&self, button: Button) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    /// Returns `true` if the button went up this frame.
+    pub fn is_released(&self, button: Button) -> bool {
+        self.released.contains(&button)
+    }
+
+    /// Last value of the axis, `0` before it moves.
+    pub fn axis(&self, axis: Axis) -> f32 {
+        self.axes.get(&axis).cloned().unwrap_or(0.0)
+    }
+}
@@ -0,0 +1,230 @@
+//! Golden-image regression testing.
+//!
+//! Renders a scene into an offscreen target and compares the result with
+//! a stored reference image, so shader and camera changes can be checked
+//! by `cargo test`.
+//!
+//! Set the `RIER_BLESS` environment variable to (re)write the reference
+//! images instead of comparing against them.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rier::Context;
+//! use rier::context::{Surface, DrawError};
+//! use rier::golden::Golden;
+//! # fn draw_scene<S: Surface>(_: &mut S) -> Result<(), DrawError> { Ok(()) }
+//!
+//! let gfx = Context::headless((64, 64)).gfx();
+//! let golden = Golden::new(gfx, (64, 64)).tolerance(2);
+//! // `draw_scene` calls `Renderer::draw_with_target` for each object.
+//! golden.check("tests/golden/scene.png", |target| draw_scene(target)).unwrap();
+//! ```
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use image::{self, RgbaImage, Rgba, ImageError};
+use glium::texture::RawImage2d;
+use glium::framebuffer::SimpleFrameBuffer;
+use context::{Gfx, Surface, DrawError};
+use target::{RenderTarget, TargetError};
+use utils::raw_to_image;
+
+
+/// Offscreen renderer that checks results against reference images.
+pub struct Golden {
+    gfx: Gfx,
+    dimensions: (u32, u32),
+    tolerance: u8,
+    clear_color: (f32, f32, f32, f32),
+}
+
+
+impl Golden {
+    /// Creates a checker that renders images of the given size.
+    pub fn new(gfx: Gfx, dimensions: (u32, u32)) -> Golden {
+        Golden {
+            gfx: gfx,
+            dimensions: dimensions,
+            tolerance: 0,
+            clear_color: (0.0, 0.0, 0.0, 0.0),
+        }
+    }
+
+    /// Sets the maximum allowed difference of each pixel channel, default `0`.
+    pub fn tolerance(self, tolerance: u8) -> Golden {
+        Golden { tolerance: tolerance, ..self }
+    }
+
+    /// Sets clear color.
+    pub fn clear_color(self, r: f32, g: f32, b: f32, a: f32) -> Golden {
+        Golden { clear_color: (r, g, b, a), ..self }
+    }
+
+    /// Renders offscreen and returns the image.
+    ///
+    /// The callback draws into the target, usually by
+    /// `Renderer::draw_with_target`.
+    pub fn render<F>(&self, draw: F) -> Result<RgbaImage, Error>
+        where F: FnOnce(&mut SimpleFrameBuffer) -> Result<(), DrawError>
+    {
+        let target = try!(RenderTarget::with_dimensions(&self.gfx, self.dimensions));
+        {
+            let mut surface = try!(target.surface());
+            surface.clear_color_and_depth(self.clear_color, 1.0);
+            try!(draw(&mut surface));
+        }
+        let raw: RawImage2d<u8> = target.texture().read();
+        Ok(raw_to_image(raw))
+    }
+
+    /// Renders offscreen and compares the image with the reference file.
+    ///
+    /// On mismatch the rendered image and a diff image are written next to
+    /// the reference, as `<name>.actual.png` and `<name>.diff.png`.
+    pub fn check<P, F>(&self, reference: P, draw: F) -> Result<(), Error>
+        where P: AsRef<Path>,
+              F: FnOnce(&mut SimpleFrameBuffer) -> Result<(), DrawError>
+    {
+        let reference = reference.as_ref();
+        let actual = try!(self.render(draw));
+        // The first bless, or a missing reference, may have no directory.
+        if let Some(parent) = reference.parent() {
+            if !parent.as_os_str().is_empty() {
+                try!(fs::create_dir_all(parent));
+            }
+        }
+        if env::var_os("RIER_BLESS").is_some() {
+            return Ok(try!(actual.save(reference)));
+        }
+        let expected = match image::open(reference) {
+            Ok(image) => image.to_rgba(),
+            Err(err) => {
+                try!(actual.save(sibling(reference, "actual")));
+                return Err(Error::Reference(err));
+            }
+        };
+        let comparison = compare(&actual, &expected, self.tolerance);
+        if comparison.is_match() {
+            return Ok(());
+        }
+        try!(actual.save(sibling(reference, "actual")));
+        if let Some(ref diff) = comparison.diff {
+            try!(diff.save(sibling(reference, "diff")));
+        }
+        Err(Error::Mismatch(comparison))
+    }
+}
+
+
+/// Path of an output file next to the reference image.
+fn sibling(reference: &Path, suffix: &str) -> PathBuf {
+    let stem = reference.file_stem().and_then(|s| s.to_str()).unwrap_or("golden");
+    reference.with_file_name(format!("{}.{}.png", stem, suffix))
+}
+
+
+/// Result of comparing two images.
+#[derive(Debug)]
+pub struct Comparison {
+    /// Number of pixels beyond the tolerance.
+    pub mismatched: usize,
+    /// Image that marks mismatched pixels red, `None` if sizes differ.
+    pub diff: Option<RgbaImage>,
+}
+
+
+impl Comparison {
+    /// Returns `true` if the images are considered equal.
+    pub fn is_match(&self) -> bool {
+        self.mismatched == 0 && self.diff.is_some()
+    }
+}
+
+
+/// Compares two images pixel by pixel.
+///
+/// # Example
+///
+/// ```
+/// extern crate image;
+/// extern crate rier;
+///
+/// use rier::golden::compare;
+/// use image::{RgbaImage, Rgba};
+///
+/// # fn main() {
+/// let a = RgbaImage::from_pixel(2, 2, Rgba([10, 10, 10, 255]));
+/// let b = RgbaImage::from_pixel(2, 2, Rgba([12, 10, 10, 255]));
+/// assert!(compare(&a, &b, 2).is_match());
+/// assert_eq!(compare(&a, &b, 1).mismatched, 4);
+/// # }
+/// ```
+pub fn compare(actual: &RgbaImage, expected: &RgbaImage, tolerance: u8) -> Comparison {
+    if actual.dimensions() != expected.dimensions() {
+        let (w, h) = actual.dimensions();
+        return Comparison {
+            mismatched: (w * h) as usize,
+            diff: None,
+        };
+    }
+    let mut mismatched = 0;
+    let (w, h) = actual.dimensions();
+    let mut diff = RgbaImage::new(w, h);
+    for (x, y, pixel) in diff.enumerate_pixels_mut() {
+        let a = actual.get_pixel(x, y);
+        let e = expected.get_pixel(x, y);
+        let over = a.data.iter().zip(e.data.iter()).any(|(&a, &e)| {
+            (a as i16 - e as i16).abs() > tolerance as i16
+        });
+        *pixel = if over {
+            mismatched += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            // Faded copy of the expected image.
+            Rgba([e[0] / 4, e[1] / 4, e[2] / 4, 255])
+        };
+    }
+    Comparison {
+        mismatched: mismatched,
+        diff: Some(diff),
+    }
+}
+
+
+/// Errors which can occur when checking a golden image.
+#[derive(Debug)]
+pub enum Error {
+    /// Offscreen target create failure.
+    Target(TargetError),
+    /// Draw failure.
+    Draw(DrawError),
+    /// Reference image load failure.
+    Reference(ImageError),
+    /// Output image write failure.
+    Io(io::Error),
+    /// Rendered image differs from the reference.
+    Mismatch(Comparison),
+}
+
+
+impl From<TargetError> for Error {
+    fn from(err: TargetError) -> Error {
+        Error::Target(err)
+    }
+}
+
+
+impl From<DrawError> for Error {
+    fn from(err: DrawError) -> Error {
+        Error::Draw(err)
+    }
+}
+
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
@@ -0,0 +1,712 @@
+//! Window input events.
+//!
+//! `Context::pump_events` reads the window events and notifies them as
+//! `InputEvent`, which do not depend on the windowing library.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rier::Context;
+//! use rier::event::Return;
+//! use rier::input::{InputEvent, Key};
+//!
+//! let gfx = Context::create("Input", (800, 600)).gfx();
+//! gfx.input_notifier().register(|event| {
+//!     match *event {
+//!         InputEvent::KeyDown(ref key) if key.key == Some(Key::Space) => println!("jump"),
+//!         InputEvent::Text(c) => println!("typed {}", c),
+//!         _ => (),
+//!     }
+//!     Return::Next
+//! });
+//! while !gfx.close_requested() {
+//!     gfx.pump_events().unwrap();
+//! }
+//! ```
+//!
+//! To poll instead, read the `Input` state of `Context::input` after
+//! pumping the events of the frame.
+use std::fmt;
+use std::str::FromStr;
+use std::collections::{HashSet, BTreeMap};
+use context::Resize;
+use camera::Camera2D;
+use event::{Listener, Return};
+use gamepad::{GamepadEvent, GamepadId, GamepadState};
+
+
+/// When `Context::input` starts a new frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFrame {
+    /// On each `Context::pump_events`.
+    Pump,
+    /// Only on `Context::begin_input_frame`, for fixed timestep updates.
+    Manual,
+}
+
+
+/// Input event of the window.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputEvent {
+    /// Key pressed, repeated while held.
+    KeyDown(KeyEvent),
+    /// Key released.
+    KeyUp(KeyEvent),
+    /// Character typed.
+    Text(char),
+    /// Cursor moved, position in window pixels from the top left.
+    MouseMove(i32, i32),
+    /// Mouse button pressed.
+    MouseDown(MouseButton),
+    /// Mouse button released.
+    MouseUp(MouseButton),
+    /// Mouse wheel or touchpad scrolled.
+    Scroll(ScrollDelta),
+    /// Window gained (`true`) or lost focus.
+    Focus(bool),
+    /// Framebuffer size or HiDPI factor changed.
+    Resize(Resize),
+    /// Window close requested.
+    Close,
+    /// Gamepad input, from the context gamepad backend.
+    Gamepad(GamepadEvent),
+}
+
+
+/// Keyboard event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    /// Key by layout, `None` if unknown.
+    pub key: Option<Key>,
+    /// Hardware dependent key code.
+    pub scancode: u32,
+    /// Modifiers held after this event.
+    pub modifiers: Modifiers,
+}
+
+
+/// Modifier keys state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    /// Windows or Command key.
+    pub logo: bool,
+}
+
+
+/// Mouse buttons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Other(u8),
+}
+
+
+/// Scroll amount, positive is up and right.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScrollDelta {
+    /// Lines or rows, from a mouse wheel.
+    Lines(f32, f32),
+    /// Pixels, from a touchpad.
+    Pixels(f32, f32),
+}
+
+
+/// Keys by layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Key {
+    /// The '0' key over the letters.
+    Key0,
+    Key1,
+    Key2,
+    Key3,
+    Key4,
+    Key5,
+    Key6,
+    Key7,
+    Key8,
+    Key9,
+
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+
+    Escape,
+    Tab,
+    CapsLock,
+    Space,
+    Enter,
+    Backspace,
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    PrintScreen,
+    ScrollLock,
+    Pause,
+
+    Left,
+    Up,
+    Right,
+    Down,
+
+    LShift,
+    RShift,
+    LControl,
+    RControl,
+    LAlt,
+    RAlt,
+    LLogo,
+    RLogo,
+
+    Minus,
+    Equals,
+    LBracket,
+    RBracket,
+    Backslash,
+    Semicolon,
+    Apostrophe,
+    Grave,
+    Comma,
+    Period,
+    Slash,
+
+    NumLock,
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    NumpadAdd,
+    NumpadSubtract,
+    NumpadMultiply,
+    NumpadDivide,
+    NumpadDecimal,
+    NumpadEnter,
+}
+
+impl Key {
+    /// Name of the key, the variant name, e.g. `"Space"` or `"F1"`.
+    ///
+    /// Used by the action config and the input recordings, so names never
+    /// change.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Key::Key0 => "Key0",
+            Key::Key1 => "Key1",
+            Key::Key2 => "Key2",
+            Key::Key3 => "Key3",
+            Key::Key4 => "Key4",
+            Key::Key5 => "Key5",
+            Key::Key6 => "Key6",
+            Key::Key7 => "Key7",
+            Key::Key8 => "Key8",
+            Key::Key9 => "Key9",
+            Key::A => "A",
+            Key::B => "B",
+            Key::C => "C",
+            Key::D => "D",
+            Key::E => "E",
+            Key::F => "F",
+            Key::G => "G",
+            Key::H => "H",
+            Key::I => "I",
+            Key::J => "J",
+            Key::K => "K",
+            Key::L => "L",
+            Key::M => "M",
+            Key::N => "N",
+            Key::O => "O",
+            Key::P => "P",
+            Key::Q => "Q",
+            Key::R => "R",
+            Key::S => "S",
+            Key::T => "T",
+            Key::U => "U",
+            Key::V => "V",
+            Key::W => "W",
+            Key::X => "X",
+            Key::Y => "Y",
+            Key::Z => "Z",
+            Key::F1 => "F1",
+            Key::F2 => "F2",
+            Key::F3 => "F3",
+            Key::F4 => "F4",
+            Key::F5 => "F5",
+            Key::F6 => "F6",
+            Key::F7 => "F7",
+            Key::F8 => "F8",
+            Key::F9 => "F9",
+            Key::F10 => "F10",
+            Key::F11 => "F11",
+            Key::F12 => "F12",
+            Key::Escape => "Escape",
+            Key::Tab => "Tab",
+            Key::CapsLock => "CapsLock",
+            Key::Space => "Space",
+            Key::Enter => "Enter",
+            Key::Backspace => "Backspace",
+            Key::Insert => "Insert",
+            Key::Delete => "Delete",
+            Key::Home => "Home",
+            Key::End => "End",
+            Key::PageUp => "PageUp",
+            Key::PageDown => "PageDown",
+            Key::PrintScreen => "PrintScreen",
+            Key::ScrollLock => "ScrollLock",
+            Key::Pause => "Pause",
+            Key::Left => "Left",
+            Key::Up => "Up",
+            Key::Right => "Right",
+            Key::Down => "Down",
+            Key::LShift => "LShift",
+            Key::RShift => "RShift",
+            Key::LControl => "LControl",
+            Key::RControl => "RControl",
+            Key::LAlt => "LAlt",
+            Key::RAlt => "RAlt",
+            Key::LLogo => "LLogo",
+            Key::RLogo => "RLogo",
+            Key::Minus => "Minus",
+            Key::Equals => "Equals",
+            Key::LBracket => "LBracket",
+            Key::RBracket => "RBracket",
+            Key::Backslash => "Backslash",
+            Key::Semicolon => "Semicolon",
+            Key::Apostrophe => "Apostrophe",
+            Key::Grave => "Grave",
+            Key::Comma => "Comma",
+            Key::Period => "Period",
+            Key::Slash => "Slash",
+            Key::NumLock => "NumLock",
+            Key::Numpad0 => "Numpad0",
+            Key::Numpad1 => "Numpad1",
+            Key::Numpad2 => "Numpad2",
+            Key::Numpad3 => "Numpad3",
+            Key::Numpad4 => "Numpad4",
+            Key::Numpad5 => "Numpad5",
+            Key::Numpad6 => "Numpad6",
+            Key::Numpad7 => "Numpad7",
+            Key::Numpad8 => "Numpad8",
+            Key::Numpad9 => "Numpad9",
+            Key::NumpadAdd => "NumpadAdd",
+            Key::NumpadSubtract => "NumpadSubtract",
+            Key::NumpadMultiply => "NumpadMultiply",
+            Key::NumpadDivide => "NumpadDivide",
+            Key::NumpadDecimal => "NumpadDecimal",
+            Key::NumpadEnter => "NumpadEnter",
+        }
+    }
+}
+
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+
+impl FromStr for Key {
+    type Err = ();
+
+    /// Parses the name returned by `name`.
+    fn from_str(s: &str) -> Result<Key, ()> {
+        let key = match s {
+            "Key0" => Key::Key0,
+            "Key1" => Key::Key1,
+            "Key2" => Key::Key2,
+            "Key3" => Key::Key3,
+            "Key4" => Key::Key4,
+            "Key5" => Key::Key5,
+            "Key6" => Key::Key6,
+            "Key7" => Key::Key7,
+            "Key8" => Key::Key8,
+            "Key9" => Key::Key9,
+            "A" => Key::A,
+            "B" => Key::B,
+            "C" => Key::C,
+            "D" => Key::D,
+            "E" => Key::E,
+            "F" => Key::F,
+            "G" => Key::G,
+            "H" => Key::H,
+            "I" => Key::I,
+            "J" => Key::J,
+            "K" => Key::K,
+            "L" => Key::L,
+            "M" => Key::M,
+            "N" => Key::N,
+            "O" => Key::O,
+            "P" => Key::P,
+            "Q" => Key::Q,
+            "R" => Key::R,
+            "S" => Key::S,
+            "T" => Key::T,
+            "U" => Key::U,
+            "V" => Key::V,
+            "W" => Key::W,
+            "X" => Key::X,
+            "Y" => Key::Y,
+            "Z" => Key::Z,
+            "F1" => Key::F1,
+            "F2" => Key::F2,
+            "F3" => Key::F3,
+            "F4" => Key::F4,
+            "F5" => Key::F5,
+            "F6" => Key::F6,
+            "F7" => Key::F7,
+            "F8" => Key::F8,
+            "F9" => Key::F9,
+            "F10" => Key::F10,
+            "F11" => Key::F11,
+            "F12" => Key::F12,
+            "Escape" => Key::Escape,
+            "Tab" => Key::Tab,
+            "CapsLock" => Key::CapsLock,
+            "Space" => Key::Space,
+            "Enter" => Key::Enter,
+            "Backspace" => Key::Backspace,
+            "Insert" => Key::Insert,
+            "Delete" => Key::Delete,
+            "Home" => Key::Home,
+            "End" => Key::End,
+            "PageUp" => Key::PageUp,
+            "PageDown" => Key::PageDown,
+            "PrintScreen" => Key::PrintScreen,
+            "ScrollLock" => Key::ScrollLock,
+            "Pause" => Key::Pause,
+            "Left" => Key::Left,
+            "Up" => Key::Up,
+            "Right" => Key::Right,
+            "Down" => Key::Down,
+            "LShift" => Key::LShift,
+            "RShift" => Key::RShift,
+            "LControl" => Key::LControl,
+            "RControl" => Key::RControl,
+            "LAlt" => Key::LAlt,
+            "RAlt" => Key::RAlt,
+            "LLogo" => Key::LLogo,
+            "RLogo" => Key::RLogo,
+            "Minus" => Key::Minus,
+            "Equals" => Key::Equals,
+            "LBracket" => Key::LBracket,
+            "RBracket" => Key::RBracket,
+            "Backslash" => Key::Backslash,
+            "Semicolon" => Key::Semicolon,
+            "Apostrophe" => Key::Apostrophe,
+            "Grave" => Key::Grave,
+            "Comma" => Key::Comma,
+            "Period" => Key::Period,
+            "Slash" => Key::Slash,
+            "NumLock" => Key::NumLock,
+            "Numpad0" => Key::Numpad0,
+            "Numpad1" => Key::Numpad1,
+            "Numpad2" => Key::Numpad2,
+            "Numpad3" => Key::Numpad3,
+            "Numpad4" => Key::Numpad4,
+            "Numpad5" => Key::Numpad5,
+            "Numpad6" => Key::Numpad6,
+            "Numpad7" => Key::Numpad7,
+            "Numpad8" => Key::Numpad8,
+            "Numpad9" => Key::Numpad9,
+            "NumpadAdd" => Key::NumpadAdd,
+            "NumpadSubtract" => Key::NumpadSubtract,
+            "NumpadMultiply" => Key::NumpadMultiply,
+            "NumpadDivide" => Key::NumpadDivide,
+            "NumpadDecimal" => Key::NumpadDecimal,
+            "NumpadEnter" => Key::NumpadEnter,
+            _ => return Err(()),
+        };
+        Ok(key)
+    }
+}
+
+
+
+/// Keyboard and mouse state of the current frame.
+///
+/// `Context::pump_events` starts a new frame and updates it, so "pressed"
+/// and "released" cover the events of one `pump_events` call, usually one
+/// loop iteration.
+///
+/// A fixed timestep loop runs zero or more updates per iteration, so an
+/// update could see a press twice or miss it. Set `InputFrame::Manual` on
+/// the context and call `Context::begin_input_frame` at the end of each
+/// update, presses are then kept until an update has seen them.
+///
+/// # Example
+///
+/// ```
+/// use rier::input::{Input, InputEvent, KeyEvent, Key, Modifiers, MouseButton};
+///
+/// let space = KeyEvent {
+///     key: Some(Key::Space),
+///     scancode: 57,
+///     modifiers: Modifiers::default(),
+/// };
+/// let mut input = Input::new();
+/// input.begin_frame();
+/// input.handle(&InputEvent::KeyDown(space));
+/// input.handle(&InputEvent::MouseDown(MouseButton::Left));
+/// assert!(input.is_pressed(Key::Space));
+/// assert!(input.is_held(Key::Space));
+///
+/// input.begin_frame();
+/// input.handle(&InputEvent::KeyDown(space)); // Key repeat.
+/// assert!(!input.is_pressed(Key::Space));
+/// assert!(input.is_button_held(MouseButton::Left));
+///
+/// input.begin_frame();
+/// input.handle(&InputEvent::KeyUp(space));
+/// assert!(input.is_released(Key::Space));
+/// assert!(!input.is_held(Key::Space));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Input {
+    held: HashSet<Key>,
+    pressed: HashSet<Key>,
+    released: HashSet<Key>,
+    buttons_held: HashSet<MouseButton>,
+    buttons_pressed: HashSet<MouseButton>,
+    buttons_released: HashSet<MouseButton>,
+    modifiers: Modifiers,
+    cursor: Option<(i32, i32)>,
+    mouse_delta: (i32, i32),
+    scroll: (f32, f32),
+    text: String,
+    gamepads: BTreeMap<GamepadId, GamepadState>,
+}
+
+
+impl Input {
+    pub fn new() -> Input {
+        Input::default()
+    }
+
+    /// Clears the per-frame state, held keys and buttons stay held.
+    pub fn begin_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+        self.mouse_delta = (0, 0);
+        self.scroll = (0.0, 0.0);
+        self.text.clear();
+        let disconnected: Vec<GamepadId> = self.gamepads
+            .iter()
+            .filter(|&(_, pad)| !pad.is_connected())
+            .map(|(&id, _)| id)
+            .collect();
+        for id in disconnected {
+            self.gamepads.remove(&id);
+        }
+        for pad in self.gamepads.values_mut() {
+            pad.begin_frame();
+        }
+    }
+
+    /// Updates the state by an event.
+    pub fn handle(&mut self, event: &InputEvent) {
+        match *event {
+            InputEvent::KeyDown(ref e) => {
+                self.modifiers = e.modifiers;
+                if let Some(key) = e.key {
+                    if self.held.insert(key) {
+                        self.pressed.insert(key);
+                    }
+                }
+            }
+            InputEvent::KeyUp(ref e) => {
+                self.modifiers = e.modifiers;
+                if let Some(key) = e.key {
+                    if self.held.remove(&key) {
+                        self.released.insert(key);
+                    }
+                }
+            }
+            InputEvent::Text(c) => self.text.push(c),
+            InputEvent::MouseMove(x, y) => {
+                if let Some((last_x, last_y)) = self.cursor {
+                    self.mouse_delta.0 += x - last_x;
+                    self.mouse_delta.1 += y - last_y;
+                }
+                self.cursor = Some((x, y));
+            }
+            InputEvent::MouseDown(button) => {
+                if self.buttons_held.insert(button) {
+                    self.buttons_pressed.insert(button);
+                }
+            }
+            InputEvent::MouseUp(button) => {
+                if self.buttons_held.remove(&button) {
+                    self.buttons_released.insert(button);
+                }
+            }
+            InputEvent::Scroll(delta) => {
+                let (x, y) = match delta {
+                    ScrollDelta::Lines(x, y) => (x, y),
+                    // Roughly one line.
+                    ScrollDelta::Pixels(x, y) => (x / 20.0, y / 20.0),
+                };
+                self.scroll.0 += x;
+                self.scroll.1 += y;
+            }
+            InputEvent::Focus(false) => {
+                // Releases are not received while unfocused.
+                self.released.extend(self.held.drain());
+                self.buttons_released.extend(self.buttons_held.drain());
+                self.modifiers = Modifiers::default();
+            }
+            InputEvent::Gamepad(GamepadEvent::Connected(id, ref name)) => {
+                self.gamepads.insert(id, GamepadState::new(name));
+            }
+            InputEvent::Gamepad(ref e) => {
+                let id = match *e {
+                    GamepadEvent::ButtonDown(id, _) |
+                    GamepadEvent::ButtonUp(id, _) |
+                    GamepadEvent::AxisMoved(id, _, _) => id,
+                    GamepadEvent::Disconnected(id) => {
+                        // Kept until the next frame, so the releases can be read.
+                        if let Some(pad) = self.gamepads.get_mut(&id) {
+                            pad.handle(e);
+                        }
+                        return;
+                    }
+                    _ => return,
+                };
+                self.gamepads.entry(id).or_insert_with(GamepadState::default).handle(e);
+            }
+            _ => (),
+        }
+    }
+
+    /// Returns `true` if the key is down.
+    pub fn is_held(&self, key: Key) -> bool {
+        self.held.contains(&key)
+    }
+
+    /// Returns `true` if the key went down this frame.
+    pub fn is_pressed(&self, key: Key) -> bool {
+        self.pressed.contains(&key)
+    }
+
+    /// Returns `true` if the key went up this frame.
+    pub fn is_released(&self, key: Key) -> bool {
+        self.released.contains(&key)
+    }
+
+    /// Returns `true` if the mouse button is down.
+    pub fn is_button_held(&self, button: MouseButton) -> bool {
+        self.buttons_held.contains(&button)
+    }
+
+    /// Returns `true` if the mouse button went down this frame.
+    pub fn is_button_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+
+    /// Returns `true` if the mouse button went up this frame.
+    pub fn is_button_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+
+    /// Modifier keys held.
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    /// Cursor position in window pixels from the top left, `None` before
+    /// the cursor first moves over the window.
+    pub fn cursor(&self) -> Option<(i32, i32)> {
+        self.cursor
+    }
+
+    /// Cursor position in the world coordinate of the camera.
+    pub fn cursor_world(&self, camera: &Camera2D) -> Option<(f32, f32)> {
+        self.cursor.map(|(x, y)| camera.screen_to_world((x as f32, y as f32)))
+    }
+
+    /// Cursor movement this frame in window pixels.
+    pub fn mouse_delta(&self) -> (i32, i32) {
+        self.mouse_delta
+    }
+
+    /// Scrolled lines this frame, touchpad pixels are converted to lines.
+    pub fn scroll(&self) -> (f32, f32) {
+        self.scroll
+    }
+
+    /// Connected gamepads, in id order.
+    ///
+    /// A gamepad disconnected this frame is still listed, with its held
+    /// buttons released.
+    pub fn gamepads(&self) -> Vec<GamepadId> {
+        self.gamepads.keys().cloned().collect()
+    }
+
+    /// State of a connected gamepad.
+    pub fn gamepad(&self, id: GamepadId) -> Option<&GamepadState> {
+        self.gamepads.get(&id)
+    }
+
+    /// Characters typed this frame.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+
+impl Listener<InputEvent> for Input {
+    fn on_event(&mut self, event: &InputEvent) -> Return<InputEvent> {
+        self.handle(event);
+        Return::Next
+    }
+}
@@ -0,0 +1,229 @@
+//! Linux joystick devices as a gamepad backend.
+//!
+//! Reads the `/dev/input/js*` devices of the joydev driver. It is the
+//! default gamepad backend of windowed contexts on Linux, disable the
+//! `joystick` feature to leave it out.
+//!
+//! Buttons and axes are mapped by the layout of the `xpad` driver, used by
+//! Xbox pads and most pads that imitate them.
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Sender, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+use std::collections::HashMap;
+use gamepad::{Backend, GamepadEvent, GamepadId, Button, Axis};
+
+
+const DEVICES: &'static str = "/dev/input";
+const JS_EVENT_BUTTON: u8 = 0x01;
+const JS_EVENT_AXIS: u8 = 0x02;
+/// Set on the events that report the state when the device is opened.
+const JS_EVENT_INIT: u8 = 0x80;
+const AXIS_MAX: f32 = 32767.0;
+
+
+/// Event read from a device.
+enum Raw {
+    Button(u8, bool),
+    Axis(u8, i16),
+    /// The device was unplugged.
+    Closed,
+}
+
+
+/// Backend that reads the joystick devices.
+///
+/// New devices are looked for once a second. Each device is read by a
+/// thread, which ends when the device is unplugged.
+pub struct JoystickBackend {
+    devices: HashMap<PathBuf, GamepadId>,
+    next_id: usize,
+    sender: Sender<(GamepadId, Raw)>,
+    receiver: Receiver<(GamepadId, Raw)>,
+    last_scan: Option<Instant>,
+}
+
+
+impl JoystickBackend {
+    pub fn new() -> JoystickBackend {
+        let (sender, receiver) = channel();
+        JoystickBackend {
+            devices: HashMap::new(),
+            next_id: 0,
+            sender: sender,
+            receiver: receiver,
+            last_scan: None,
+        }
+    }
+
+    /// Opens the devices not opened yet.
+    fn scan(&mut self, events: &mut Vec<GamepadEvent>) {
+        let entries = match fs::read_dir(DEVICES) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            let is_joystick = path.file_name()
+                .and_then(|name| name.to_str())
+                .map(|name| name.starts_with("js"))
+                .unwrap_or(false);
+            if !is_joystick || self.devices.contains_key(&path) {
+                continue;
+            }
+            // Usually not readable without the `input` group.
+            let file = match File::open(&path) {
+                Ok(file) => file,
+                Err(_) => continue,
+            };
+            let id = GamepadId(self.next_id);
+            self.next_id += 1;
+            events.push(GamepadEvent::Connected(id, device_name(&path)));
+            self.devices.insert(path, id);
+            let sender = self.sender.clone();
+            thread::spawn(move || read_device(file, id, sender));
+        }
+    }
+}
+
+
+impl Default for JoystickBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+impl Backend for JoystickBackend {
+    fn poll(&mut self) -> Vec<GamepadEvent> {
+        let mut events = Vec::new();
+        let now = Instant::now();
+        let rescan = match self.last_scan {
+            Some(last) => now - last >= Duration::from_secs(1),
+            None => true,
+        };
+        if rescan {
+            self.last_scan = Some(now);
+            self.scan(&mut events);
+        }
+        while let Ok((id, raw)) = self.receiver.try_recv() {
+            match raw {
+                Raw::Button(number, down) => {
+                    if let Some(button) = button(number) {
+                        events.push(if down {
+                            GamepadEvent::ButtonDown(id, button)
+                        } else {
+                            GamepadEvent::ButtonUp(id, button)
+                        });
+                    }
+                }
+                Raw::Axis(number, value) => axis(id, number, value, &mut events),
+                Raw::Closed => {
+                    self.devices.retain(|_, device| *device != id);
+                    events.push(GamepadEvent::Disconnected(id));
+                }
+            }
+        }
+        events
+    }
+}
+
+
+/// Name of the device from sysfs, the path if not found.
+fn device_name(path: &Path) -> String {
+    let node = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+    let sys = Path::new("/sys/class/input").join(node).join("device/name");
+    let mut name = String::new();
+    match File::open(sys).and_then(|mut file| file.read_to_string(&mut name)) {
+        Ok(_) => name.trim().to_string(),
+        Err(_) => path.display().to_string(),
+    }
+}
+
+
+/// Sends the events of a device until it is unplugged or the backend is
+/// dropped.
+fn read_device(mut file: File, id: GamepadId, sender: Sender<(GamepadId, Raw)>) {
+    // `struct js_event`: u32 time, i16 value, u8 type, u8 number.
+    let mut buffer = [0u8; 8];
+    while file.read_exact(&mut buffer).is_ok() {
+        let (low, high) = if cfg!(target_endian = "little") {
+            (buffer[4], buffer[5])
+        } else {
+            (buffer[5], buffer[4])
+        };
+        let value = (low as u16 | (high as u16) << 8) as i16;
+        let number = buffer[7];
+        let raw = match buffer[6] & !JS_EVENT_INIT {
+            JS_EVENT_BUTTON => Raw::Button(number, value != 0),
+            JS_EVENT_AXIS => Raw::Axis(number, value),
+            _ => continue,
+        };
+        if sender.send((id, raw)).is_err() {
+            return;
+        }
+    }
+    let _ = sender.send((id, Raw::Closed));
+}
+
+
+fn button(number: u8) -> Option<Button> {
+    let button = match number {
+        0 => Button::South,
+        1 => Button::East,
+        2 => Button::West,
+        3 => Button::North,
+        4 => Button::LeftBumper,
+        5 => Button::RightBumper,
+        6 => Button::Select,
+        7 => Button::Start,
+        8 => Button::Mode,
+        9 => Button::LeftStick,
+        10 => Button::RightStick,
+        _ => return None,
+    };
+    Some(button)
+}
+
+
+/// Converts an axis event, the directional pad is reported as two axes.
+fn axis(id: GamepadId, number: u8, value: i16, events: &mut Vec<GamepadEvent>) {
+    let stick = value as f32 / AXIS_MAX;
+    let trigger = (value as f32 + AXIS_MAX) / (2.0 * AXIS_MAX);
+    let (axis, value) = match number {
+        0 => (Axis::LeftX, stick),
+        // Down is positive.
+        1 => (Axis::LeftY, -stick),
+        2 => (Axis::LeftTrigger, trigger),
+        3 => (Axis::RightX, stick),
+        4 => (Axis::RightY, -stick),
+        5 => (Axis::RightTrigger, trigger),
+        6 => return dpad(id, value, Button::DPadLeft, Button::DPadRight, events),
+        7 => return dpad(id, value, Button::DPadUp, Button::DPadDown, events),
+        _ => return,
+    };
+    events.push(GamepadEvent::AxisMoved(id, axis, value.max(-1.0).min(1.0)));
+}
+
+
+fn dpad(id: GamepadId,
+        value: i16,
+        negative: Button,
+        positive: Button,
+        events: &mut Vec<GamepadEvent>) {
+    // Releases of buttons that are not held are ignored by the state.
+    let (down, up) = if value < 0 {
+        (Some(negative), positive)
+    } else if value > 0 {
+        (Some(positive), negative)
+    } else {
+        events.push(GamepadEvent::ButtonUp(id, negative));
+        (None, positive)
+    };
+    events.push(GamepadEvent::ButtonUp(id, up));
+    if let Some(down) = down {
+        events.push(GamepadEvent::ButtonDown(id, down));
+    }
+}
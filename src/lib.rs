@@ -14,13 +14,23 @@ mod id;
 mod utils;
 mod transform;
 pub mod main_loop;
+pub mod schedule;
 pub mod context;
 pub mod camera;
 pub mod event;
+pub mod input;
+pub mod gamepad;
+#[cfg(all(feature = "joystick", target_os = "linux"))]
+pub mod joystick;
+pub mod record;
+pub mod action;
 pub mod loader;
 pub mod texture;
 pub mod mesh;
 pub mod render;
+pub mod target;
+pub mod postprocess;
+pub mod golden;
 
 
 
@@ -28,8 +38,9 @@ pub use id::Id;
 pub use mesh::Mesh;
 pub use main_loop::Loop;
 pub use render::{Renderer, Shader};
-pub use camera::{Camera2D, Camera3D};
+pub use target::RenderTarget;
+pub use camera::{Camera2D, Camera3D, OrbitController};
 pub use transform::Transform;
-pub use context::{Gfx, Context};
+pub use context::{Gfx, Context, ContextBuilder};
 pub use utils::{Matrix, AsMatrix, Cache};
-pub use glium::glutin::Event as WindowEvent;
+pub use context::WindowEvent;
@@ -1,53 +1,729 @@
 //! Main rendering loop and frame rate control.
+//!
+//! # Example
+//!
+//! Fixed timestep with interpolated rendering:
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use rier::main_loop::{Loop, Pacing, Return};
+//!
+//! let main_loop = Loop::fixed(Duration::new(0, 10000000),
+//!                             |_time| {
+//!                                 // Physics step, `delta()` is always 10ms.
+//!                                 Return::Next
+//!                             },
+//!                             |_time| {
+//!                                 // Draw, blending states by `alpha()`.
+//!                                 Return::Next
+//!                             })
+//!     .pacing(Pacing::Vsync);
+//! main_loop.start().unwrap();
+//! ```
 
 use std::time::{Duration, Instant};
 use std::thread::sleep;
+use std::collections::VecDeque;
 use fps_counter::FPSCounter;
+use schedule::Scheduler;
+use event::{EventBus, NotifyError};
+
+
+/// Loop body function without rendering step.
+pub type Nothing = fn(&mut Time) -> Return;
+
+fn nothing(_: &mut Time) -> Return {
+    Return::Next
+}
+
+
+/// Converts duration to seconds.
+pub fn secs(duration: Duration) -> f32 {
+    duration.as_secs() as f32 + duration.subsec_nanos() as f32 / 1000000000.0
+}
+
+
+/// Multiplies duration by a factor, rounded to nanoseconds.
+fn mul(duration: Duration, factor: f32) -> Duration {
+    const NANOS: u64 = 1000000000;
+    let total = duration.as_secs() * NANOS + duration.subsec_nanos() as u64;
+    let total = (total as f64 * factor as f64).round() as u64;
+    Duration::new(total / NANOS, (total % NANOS) as u32)
+}
+
+
+/// Converts seconds to duration.
+fn duration(secs: f32) -> Duration {
+    let secs = secs.max(0.0);
+    Duration::new(secs as u64, (secs.fract() * 1000000000.0) as u32)
+}
+
+
+/// How the loop waits between frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pacing {
+    /// Sleep to cap the frame rate, default 60 FPS.
+    Sleep(u32),
+    /// Never sleep, buffer swaps block until vertical blank.
+    Vsync,
+    /// Never sleep.
+    Uncapped,
+}
+
+
+/// Frame time statistics.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameStats {
+    /// Frames in the last second.
+    pub fps: usize,
+    /// Exponential moving average of the frame time.
+    pub smoothed: Duration,
+    /// Shortest frame time in the window.
+    pub min: Duration,
+    /// Longest frame time in the window.
+    pub max: Duration,
+    /// Median frame time in the window.
+    pub p50: Duration,
+    /// 95th percentile frame time in the window.
+    pub p95: Duration,
+    /// 99th percentile frame time in the window.
+    pub p99: Duration,
+    /// Time the previous frame spent running the loop body.
+    pub work: Duration,
+    /// Time the previous frame spent sleeping.
+    pub sleep: Duration,
+    /// Number of frames started, including the current one.
+    pub frames: u64,
+}
+
+
+impl FrameStats {
+    fn new() -> FrameStats {
+        let zero = Duration::new(0, 0);
+        FrameStats {
+            fps: 0,
+            smoothed: zero,
+            min: zero,
+            max: zero,
+            p50: zero,
+            p95: zero,
+            p99: zero,
+            work: zero,
+            sleep: zero,
+            frames: 0,
+        }
+    }
+}
+
+
+/// Collects frame times over a window of frames.
+struct StatsTracker {
+    window: VecDeque<Duration>,
+    /// The window frame times in order, for percentiles.
+    sorted: Vec<Duration>,
+    capacity: usize,
+    fps: FPSCounter,
+}
+
+
+impl StatsTracker {
+    fn new(capacity: usize) -> StatsTracker {
+        StatsTracker {
+            window: VecDeque::with_capacity(capacity),
+            sorted: Vec::with_capacity(capacity),
+            capacity: capacity,
+            fps: FPSCounter::new(),
+        }
+    }
+
+    /// Records the frame time of a new frame.
+    fn tick(&mut self, stats: &mut FrameStats, delta: Duration) {
+        const SMOOTHING: f32 = 0.1;
+
+        stats.fps = self.fps.tick();
+        stats.frames += 1;
+        stats.smoothed = if stats.frames == 1 {
+            delta
+        } else {
+            duration(secs(stats.smoothed) + (secs(delta) - secs(stats.smoothed)) * SMOOTHING)
+        };
+        if self.window.len() == self.capacity {
+            if let Some(oldest) = self.window.pop_front() {
+                if let Ok(index) = self.sorted.binary_search(&oldest) {
+                    self.sorted.remove(index);
+                }
+            }
+        }
+        self.window.push_back(delta);
+        let index = self.sorted.binary_search(&delta).unwrap_or_else(|index| index);
+        self.sorted.insert(index, delta);
+
+        let sorted = &self.sorted;
+        let percentile = |p: usize| sorted[(sorted.len() - 1) * p / 100];
+        stats.min = sorted[0];
+        stats.max = sorted[sorted.len() - 1];
+        stats.p50 = percentile(50);
+        stats.p95 = percentile(95);
+        stats.p99 = percentile(99);
+    }
+}
+
+
+/// Game time that can be paused and scaled.
+///
+/// Real time keeps running while paused, so UI can still animate.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use rier::main_loop::GameClock;
+///
+/// let mut clock = GameClock::new();
+/// clock.set_scale(0.5);
+/// clock.tick(Duration::from_millis(100));
+/// assert_eq!(clock.delta(), Duration::from_millis(50));
+/// assert_eq!(clock.real_delta(), Duration::from_millis(100));
+///
+/// clock.pause();
+/// clock.tick(Duration::from_millis(100));
+/// assert_eq!(clock.delta(), Duration::new(0, 0));
+///
+/// clock.step();
+/// clock.tick(Duration::from_millis(100));
+/// assert_eq!(clock.delta(), Duration::from_millis(100));
+/// ```
+#[derive(Debug, Clone)]
+pub struct GameClock {
+    paused: bool,
+    step_requested: bool,
+    step_size: Option<Duration>,
+    scale: f32,
+    max_delta: Duration,
+    real_delta: Duration,
+    delta: Duration,
+    real_elapsed: Duration,
+    elapsed: Duration,
+}
+
+
+impl GameClock {
+    /// Creates a running clock with scale `1.0`.
+    pub fn new() -> GameClock {
+        let zero = Duration::new(0, 0);
+        GameClock {
+            paused: false,
+            step_requested: false,
+            step_size: None,
+            scale: 1.0,
+            max_delta: Duration::from_millis(250),
+            real_delta: zero,
+            delta: zero,
+            real_elapsed: zero,
+            elapsed: zero,
+        }
+    }
+
+    /// Stops game time.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Continues game time.
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Advances game time by one frame while paused.
+    ///
+    /// In fixed timestep mode, one step is exactly one update.
+    pub fn step(&mut self) {
+        self.step_requested = true;
+    }
+
+    /// Sets game time speed, `0.5` for slow motion, `2.0` for fast forward.
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale.max(0.0);
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Sets the longest frame time, default 250ms.
+    ///
+    /// Longer frames, like after a debugger break, are clamped to this.
+    /// Frame times given to `Loop::step` and `Loop::run` are not clamped.
+    pub fn set_max_delta(&mut self, max: Duration) {
+        self.max_delta = max;
+    }
+
+    pub fn max_delta(&self) -> Duration {
+        self.max_delta
+    }
+
+    /// Real time of the last tick.
+    pub fn real_delta(&self) -> Duration {
+        self.real_delta
+    }
+
+    /// Game time of the last tick.
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    /// Real time since the clock was created.
+    pub fn real_elapsed(&self) -> Duration {
+        self.real_elapsed
+    }
+
+    /// Game time since the clock was created.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Advances the clock by a frame time, returns the game time delta.
+    ///
+    /// The frame time is clamped to `max_delta`.
+    pub fn tick(&mut self, real: Duration) -> Duration {
+        let real = if real > self.max_delta { self.max_delta } else { real };
+        self.advance(real)
+    }
+
+    /// Advances the clock by a frame time without clamping.
+    fn advance(&mut self, real: Duration) -> Duration {
+        let delta = if !self.paused {
+            mul(real, self.scale)
+        } else if self.step_requested {
+            self.step_size.unwrap_or(real)
+        } else {
+            Duration::new(0, 0)
+        };
+        self.step_requested = false;
+        self.real_delta = real;
+        self.delta = delta;
+        self.real_elapsed += real;
+        self.elapsed += delta;
+        delta
+    }
+}
+
+
+impl Default for GameClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+/// Frame timing passed to the loop body.
+pub struct Time {
+    delta: Duration,
+    alpha: f32,
+    stats: FrameStats,
+    clock: GameClock,
+    schedule: Scheduler,
+    events: EventBus,
+}
+
+
+impl Time {
+    fn new() -> Time {
+        Time {
+            delta: Duration::new(0, 0),
+            alpha: 1.0,
+            stats: FrameStats::new(),
+            clock: GameClock::new(),
+            schedule: Scheduler::new(),
+            events: EventBus::new(),
+        }
+    }
+
+    /// Deferred events, flushed by the loop at the `Flush` point.
+    ///
+    /// In fixed timestep mode, frames without updates flush after rendering.
+    /// A flush error stops the loop and is returned by `Loop::start`,
+    /// `step` and `run`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use std::cell::Cell;
+    /// use std::time::Duration;
+    /// use rier::Loop;
+    /// use rier::event;
+    /// use rier::main_loop::Return;
+    ///
+    /// struct Spawned(u32);
+    ///
+    /// let received = Rc::new(Cell::new(0));
+    /// let counter = received.clone();
+    /// let mut frame = 0;
+    /// let mut main_loop = Loop::new(|time| {
+    ///     if frame == 0 {
+    ///         let counter = counter.clone();
+    ///         time.events().notifier::<Spawned>().register(move |e| {
+    ///             counter.set(counter.get() + e.0 + 1);
+    ///             event::Return::Next
+    ///         });
+    ///     }
+    ///     // Notified after this update returns.
+    ///     time.events().emit(Spawned(frame));
+    ///     frame += 1;
+    ///     Return::Next
+    /// });
+    /// main_loop.step_n(3, Duration::from_millis(16)).unwrap();
+    /// // Spawned(0), Spawned(1) and Spawned(2) were received.
+    /// assert_eq!(received.get(), 1 + 2 + 3);
+    /// ```
+    pub fn events(&mut self) -> &mut EventBus {
+        &mut self.events
+    }
+
+    /// Timers advanced with game time before each update.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use std::cell::Cell;
+    /// use std::time::Duration;
+    /// use rier::Loop;
+    /// use rier::main_loop::Return;
+    ///
+    /// let fired = Rc::new(Cell::new(0));
+    /// let counter = fired.clone();
+    /// let mut frames = 0;
+    /// let mut main_loop = Loop::new(|time| {
+    ///     if frames == 0 {
+    ///         // Due at 70ms, the first update runs at 20ms.
+    ///         let counter = counter.clone();
+    ///         time.schedule().after(Duration::from_millis(50), move |_| {
+    ///             counter.set(counter.get() + 1)
+    ///         });
+    ///     }
+    ///     frames += 1;
+    ///     Return::Next
+    /// });
+    /// main_loop.step_n(3, Duration::from_millis(20));
+    /// assert_eq!(fired.get(), 0);
+    /// main_loop.step_n(2, Duration::from_millis(20));
+    /// assert_eq!(fired.get(), 1);
+    /// ```
+    pub fn schedule(&mut self) -> &mut Scheduler {
+        &mut self.schedule
+    }
+
+    /// Frame time statistics, updated at the start of each frame.
+    pub fn stats(&self) -> &FrameStats {
+        &self.stats
+    }
+
+    /// The loop clock.
+    pub fn clock(&self) -> &GameClock {
+        &self.clock
+    }
+
+    /// The loop clock, for pausing and time scaling.
+    pub fn clock_mut(&mut self) -> &mut GameClock {
+        &mut self.clock
+    }
+
+    /// Game time since the last update.
+    ///
+    /// In fixed timestep mode, this is always the step size.
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    /// Real time since the last frame, not affected by pausing and scaling.
+    pub fn real_delta(&self) -> Duration {
+        self.clock.real_delta()
+    }
+
+    /// `delta` in seconds.
+    pub fn delta_secs(&self) -> f32 {
+        secs(self.delta)
+    }
+
+    /// Interpolation factor between the last two updates, in `[0, 1)`.
+    ///
+    /// Only meaningful in the render function of a fixed timestep loop,
+    /// `1.0` otherwise.
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+}
+
+
+/// When the loop flushes `Time::events`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flush {
+    /// Before each update, events emitted by the last render are seen by
+    /// the update.
+    BeforeUpdate,
+    /// After each update, before rendering.
+    AfterUpdate,
+    /// Only when the loop body calls `EventBus::flush`.
+    Manual,
+}
+
+
+/// Calls the update function, flushing events around it.
+fn update<U>(update: &mut U, time: &mut Time, flush: Flush) -> Result<Return, NotifyError>
+    where U: FnMut(&mut Time) -> Return
+{
+    if flush == Flush::BeforeUpdate {
+        try!(time.events.flush());
+    }
+    let result = update(time);
+    if flush == Flush::AfterUpdate {
+        try!(time.events.flush());
+    }
+    Ok(result)
+}
+
+
+/// Fixed timestep settings.
+struct Fixed {
+    step: Duration,
+    max_steps: u32,
+    accumulator: Duration,
+}
+
 
 /// Main loop.
-pub struct Loop<F: FnMut(Duration) -> Return> {
+pub struct Loop<U, R>
+    where U: FnMut(&mut Time) -> Return,
+          R: FnMut(&mut Time) -> Return
+{
     last_time: Instant,
-    func: F,
-    fps: FPSCounter,
+    update: U,
+    render: R,
+    fixed: Option<Fixed>,
+    pacing: Pacing,
+    flush: Flush,
+    time: Time,
+    tracker: StatsTracker,
+}
+
+
+impl<F: FnMut(&mut Time) -> Return> Loop<F, Nothing> {
+    /// Creates with loop body function, called once per frame.
+    pub fn new(f: F) -> Loop<F, Nothing> {
+        Loop::build(f, nothing as Nothing, None)
+    }
 }
 
 
-impl<F: FnMut(Duration) -> Return> Loop<F> {
-    /// Creates with loop body function.
-    pub fn new(f: F) -> Loop<F> {
+impl<U, R> Loop<U, R>
+    where U: FnMut(&mut Time) -> Return,
+          R: FnMut(&mut Time) -> Return
+{
+    /// Creates a fixed timestep loop.
+    ///
+    /// Each frame `update` is called zero or more times with `step` as
+    /// delta, then `render` is called once.
+    ///
+    /// Panics if `step` is zero.
+    pub fn fixed(step: Duration, update: U, render: R) -> Loop<U, R> {
+        assert!(step > Duration::new(0, 0), "fixed timestep must be positive");
+        let fixed = Fixed {
+            step: step,
+            max_steps: 8,
+            accumulator: Duration::new(0, 0),
+        };
+        let mut main_loop = Loop::build(update, render, Some(fixed));
+        main_loop.time.clock.step_size = Some(step);
+        main_loop
+    }
+
+    fn build(update: U, render: R, fixed: Option<Fixed>) -> Loop<U, R> {
         Loop {
             last_time: Instant::now(),
-            func: f,
-            fps: FPSCounter::new(),
+            update: update,
+            render: render,
+            fixed: fixed,
+            pacing: Pacing::Sleep(60),
+            flush: Flush::AfterUpdate,
+            time: Time::new(),
+            tracker: StatsTracker::new(120),
+        }
+    }
+
+    /// Sets how many frames the statistics cover, default `120`.
+    pub fn stats_window(self, frames: usize) -> Loop<U, R> {
+        Loop { tracker: StatsTracker::new(frames.max(1)), ..self }
+    }
+
+    /// Sets how the loop waits between frames.
+    pub fn pacing(self, pacing: Pacing) -> Loop<U, R> {
+        Loop { pacing: pacing, ..self }
+    }
+
+    /// Sets when queued events are flushed, default `Flush::AfterUpdate`.
+    pub fn flush(self, flush: Flush) -> Loop<U, R> {
+        Loop { flush: flush, ..self }
+    }
+
+    /// Sets the maximum updates per frame in fixed timestep mode, default `8`.
+    ///
+    /// When updates fall further behind, the remaining time is dropped
+    /// instead of trying to catch up.
+    ///
+    /// Panics if `n` is zero.
+    pub fn max_steps(mut self, n: u32) -> Loop<U, R> {
+        assert!(n > 0, "fixed timestep needs at least one update per frame");
+        if let Some(ref mut fixed) = self.fixed {
+            fixed.max_steps = n;
+        }
+        self
+    }
+
+    /// Runs a frame that took `delta`, clamped to the clock `max_delta` if
+    /// the time was measured.
+    fn frame(&mut self, delta: Duration, clamp: bool) -> Result<Return, NotifyError> {
+        self.tracker.tick(&mut self.time.stats, delta);
+        let delta = if clamp {
+            self.time.clock.tick(delta)
+        } else {
+            self.time.clock.advance(delta)
+        };
+        let fixed = match self.fixed {
+            None => {
+                self.time.delta = delta;
+                self.time.alpha = 1.0;
+                self.time.schedule.advance(delta);
+                return update(&mut self.update, &mut self.time, self.flush);
+            }
+            Some(ref mut fixed) => fixed,
+        };
+        fixed.accumulator += delta;
+        let mut steps = 0;
+        while fixed.accumulator >= fixed.step {
+            if steps == fixed.max_steps {
+                // Too far behind, give up catching up.
+                fixed.accumulator = Duration::new(0, 0);
+                break;
+            }
+            self.time.delta = fixed.step;
+            self.time.schedule.advance(fixed.step);
+            if let Return::Exit = try!(update(&mut self.update, &mut self.time, self.flush)) {
+                return Ok(Return::Exit);
+            }
+            fixed.accumulator -= fixed.step;
+            steps += 1;
+        }
+        self.time.delta = fixed.step;
+        self.time.alpha = secs(fixed.accumulator) / secs(fixed.step);
+        let result = (self.render)(&mut self.time);
+        if steps == 0 && self.flush != Flush::Manual {
+            // Deliver events emitted while no update runs, like when paused.
+            try!(self.time.events.flush());
+        }
+        Ok(result)
+    }
+
+    /// Runs one frame with the given frame time, without sleeping.
+    ///
+    /// Drives the loop with a virtual clock, for tests and replays. Only
+    /// `FrameStats::fps` depends on the wall clock. The frame time is not
+    /// clamped to `GameClock::max_delta`, but `max_steps` still applies in
+    /// fixed timestep mode.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use std::cell::Cell;
+    /// use std::time::Duration;
+    /// use rier::main_loop::{Loop, Return};
+    ///
+    /// let updates = Rc::new(Cell::new(0));
+    /// let counter = updates.clone();
+    /// let mut main_loop = Loop::fixed(Duration::from_millis(10),
+    ///                                 move |_| {
+    ///                                     counter.set(counter.get() + 1);
+    ///                                     Return::Next
+    ///                                 },
+    ///                                 |_| Return::Next);
+    ///
+    /// // One simulated second.
+    /// main_loop.step_n(50, Duration::from_millis(20)).unwrap();
+    /// assert_eq!(updates.get(), 100);
+    ///
+    /// // Long frames are simulated in full.
+    /// let elapsed = Rc::new(Cell::new(Duration::new(0, 0)));
+    /// let clock = elapsed.clone();
+    /// let mut main_loop = Loop::new(move |time| {
+    ///     clock.set(time.clock().elapsed());
+    ///     Return::Next
+    /// });
+    /// main_loop.step_n(4, Duration::from_secs(1)).unwrap();
+    /// assert_eq!(elapsed.get(), Duration::from_secs(4));
+    /// ```
+    pub fn step(&mut self, delta: Duration) -> Result<Return, NotifyError> {
+        self.frame(delta, false)
+    }
+
+    /// Runs `n` frames of the same frame time, stops early on `Exit`.
+    pub fn step_n(&mut self, n: usize, delta: Duration) -> Result<Return, NotifyError> {
+        self.run((0..n).map(|_| delta))
+    }
+
+    /// Runs a frame for each frame time, stops early on `Exit`.
+    ///
+    /// Replays recorded frame times frame-for-frame.
+    pub fn run<I>(&mut self, deltas: I) -> Result<Return, NotifyError>
+        where I: IntoIterator<Item = Duration>
+    {
+        for delta in deltas {
+            if let Return::Exit = try!(self.frame(delta, false)) {
+                return Ok(Return::Exit);
+            }
         }
+        Ok(Return::Next)
     }
 
-    /// Start the loop.
-    pub fn start(mut self) {
+    /// Start the loop, returns when the loop body exits or flushing events
+    /// fails.
+    pub fn start(mut self) -> Result<(), NotifyError> {
         const ONE_SEC: u32 = 1000000000;
-        const FRAME_RATE: u32 = 60;
-        let one_frame = Duration::new(0, ONE_SEC/FRAME_RATE - 1000);
         self.last_time = Instant::now();
         loop {
-            let _fps = self.fps.tick();
-            // println!("{}", _fps);
             let now = Instant::now();
             let delta = now - self.last_time;
             self.last_time = now;
-            match (self.func)(delta) {
+            match try!(self.frame(delta, true)) {
                 Return::Exit => break,
                 Return::Next => (),
             }
-            let delta = Instant::now() - self.last_time;
-            if delta < one_frame {
-                sleep(one_frame - delta);
+            let work = Instant::now() - self.last_time;
+            let mut slept = Duration::new(0, 0);
+            if let Pacing::Sleep(frame_rate) = self.pacing {
+                let nanos = (ONE_SEC / frame_rate.max(1)).saturating_sub(1000);
+                let one_frame = Duration::new(0, nanos);
+                if work < one_frame {
+                    let before = Instant::now();
+                    sleep(one_frame - work);
+                    slept = Instant::now() - before;
+                }
             }
+            self.time.stats.work = work;
+            self.time.stats.sleep = slept;
         }
+        Ok(())
     }
 }
 
 
 /// Return value of loop body function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Return {
     /// Stop main loop.
     Exit,
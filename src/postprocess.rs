@@ -0,0 +1,581 @@
+//! Post-processing effect chain.
+//!
+//! The scene is drawn into an offscreen target, then each effect draws a
+//! full-screen triangle that reads the previous result.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rier::Context;
+//! use rier::postprocess::{PostProcess, Bloom, Tonemap, Fxaa};
+//!
+//! let gfx = Context::create("Post", (800, 600)).gfx();
+//! let mut post = PostProcess::new(&gfx).unwrap();
+//! post.push(Bloom::new(&gfx).unwrap());
+//! post.push(Tonemap::new(&gfx).unwrap());
+//! post.push(Fxaa::new(&gfx).unwrap());
+//!
+//! {
+//!     let _scene = post.begin().unwrap();
+//!     // renderer.draw_with_target(&mut _scene, &mesh, &uniforms)
+//! }
+//! gfx.frame(|frame| post.apply(&mut **frame).unwrap()).unwrap();
+//! ```
+use std::default::Default;
+use glium::DrawParameters;
+use glium::texture::{Texture2d, UncompressedFloatFormat};
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::uniforms::{Uniforms, Sampler, SamplerWrapFunction, MagnifySamplerFilter};
+use context::{Gfx, Surface, DrawError};
+use mesh::{Mesh, VertexCreationError};
+use render::{Renderer, Shader, ProgramCreationError};
+use target::{RenderTarget, TargetError};
+
+
+/// Vertex of the full-screen triangle.
+#[derive(Copy, Clone)]
+pub struct ScreenVertex {
+    position: [f32; 2],
+}
+
+implement_vertex! {ScreenVertex, position}
+
+
+/// Vertex shader shared by all passes, outputs `uv` texture coordinate.
+pub const SCREEN_VERTEX: &'static str = r#"
+    #version 330 core
+    in vec2 position;
+    out vec2 uv;
+
+    void main() {
+        uv = position * 0.5 + 0.5;
+        gl_Position = vec4(position, 0.0, 1.0);
+    }
+"#;
+
+
+/// Texture format of the intermediate targets.
+const FORMAT: UncompressedFloatFormat = UncompressedFloatFormat::F16F16F16F16;
+
+
+fn buffer(gfx: &Gfx, dimensions: (u32, u32)) -> Result<RenderTarget, TargetError> {
+    RenderTarget::with_outputs(gfx, dimensions, &["color"], FORMAT)
+}
+
+
+fn clamped(texture: &Texture2d) -> Sampler<Texture2d> {
+    texture.sampled().wrap_function(SamplerWrapFunction::Clamp)
+}
+
+
+/// A full-screen pass drawn with shader `S`.
+pub struct Pass<S: Shader<Vertex = ScreenVertex>> {
+    renderer: Renderer<S>,
+    mesh: Mesh<ScreenVertex>,
+}
+
+
+impl<S: Shader<Vertex = ScreenVertex>> Pass<S> {
+    pub fn new(gfx: &Gfx) -> Result<Pass<S>, Error> {
+        let renderer = try!(Renderer::new(gfx.clone()));
+        let mesh = try!(Mesh::new(&renderer,
+                                  &[ScreenVertex { position: [-1.0, -1.0] },
+                                    ScreenVertex { position: [3.0, -1.0] },
+                                    ScreenVertex { position: [-1.0, 3.0] }]));
+        Ok(Pass {
+            renderer: renderer,
+            mesh: mesh,
+        })
+    }
+
+    /// Draws the full-screen triangle.
+    pub fn draw<T, U>(&self, target: &mut T, uniforms: &U) -> Result<(), DrawError>
+        where T: Surface,
+              U: Uniforms
+    {
+        self.renderer.draw_with_target(target, &self.mesh, uniforms)
+    }
+}
+
+
+/// A step of the chain.
+pub trait Effect {
+    /// Reads `input` and draws the result into `output`.
+    fn apply(&self, input: &Texture2d, output: &mut SimpleFrameBuffer) -> Result<(), Error>;
+
+    /// Called when the chain size changed.
+    fn resize(&mut self, _dimensions: (u32, u32)) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+
+/// Ordered list of effects with two targets to ping-pong between.
+pub struct PostProcess {
+    gfx: Gfx,
+    effects: Vec<Box<Effect>>,
+    /// Multisampled scene, the first target is the scene without
+    /// multisampling.
+    scene: Option<RenderTarget>,
+    targets: [RenderTarget; 2],
+}
+
+
+impl PostProcess {
+    /// Creates an empty chain of the context framebuffer size.
+    pub fn new(gfx: &Gfx) -> Result<PostProcess, Error> {
+        PostProcess::with_samples(gfx, 0)
+    }
+
+    /// Creates an empty chain that draws the scene with multisampling.
+    pub fn with_samples(gfx: &Gfx, samples: u32) -> Result<PostProcess, Error> {
+        let dimensions = gfx.dimensions();
+        let scene = if samples > 0 {
+            Some(try!(RenderTarget::with_samples(gfx, dimensions, &["color"], FORMAT, samples)))
+        } else {
+            None
+        };
+        Ok(PostProcess {
+            gfx: gfx.clone(),
+            effects: Vec::new(),
+            scene: scene,
+            targets: [try!(buffer(gfx, dimensions)), try!(buffer(gfx, dimensions))],
+        })
+    }
+
+    /// Appends an effect to the chain.
+    pub fn push<E: Effect + 'static>(&mut self, effect: E) {
+        self.effects.push(Box::new(effect));
+    }
+
+    /// Removes all effects.
+    pub fn clear(&mut self) {
+        self.effects.clear();
+    }
+
+    /// Returns the cleared scene surface to draw into.
+    ///
+    /// Follows the context framebuffer size.
+    pub fn begin(&mut self) -> Result<SimpleFrameBuffer, Error> {
+        let dimensions = self.gfx.dimensions();
+        if dimensions != self.targets[0].dimensions() {
+            if let Some(ref mut scene) = self.scene {
+                try!(scene.resize(dimensions));
+            }
+            for target in &mut self.targets {
+                try!(target.resize(dimensions));
+            }
+            for effect in &mut self.effects {
+                try!(effect.resize(dimensions));
+            }
+        }
+        let mut surface = try!(self.scene().surface());
+        surface.clear_color_and_depth((0.0, 0.0, 0.0, 0.0), 1.0);
+        Ok(surface)
+    }
+
+    fn scene(&self) -> &RenderTarget {
+        self.scene.as_ref().unwrap_or(&self.targets[0])
+    }
+
+    /// Runs the effects on the scene and draws the result to `output`.
+    pub fn apply<T: Surface>(&self, output: &mut T) -> Result<(), Error> {
+        let scene = self.scene();
+        try!(scene.resolve());
+        // Don't draw into the first target while it holds the scene.
+        let first = if self.scene.is_some() { 0 } else { 1 };
+        let mut input = scene.texture();
+        for (i, effect) in self.effects.iter().enumerate() {
+            let target = &self.targets[(first + i) % 2];
+            {
+                let mut surface = try!(target.surface());
+                try!(effect.apply(input, &mut surface));
+            }
+            input = target.texture();
+        }
+        input.as_surface().fill(&*output, MagnifySamplerFilter::Nearest);
+        Ok(())
+    }
+}
+
+
+macro_rules! screen_shader {
+    ($name: ident, $fragment: expr) => {
+        struct $name;
+
+        impl Shader for $name {
+            type Vertex = ScreenVertex;
+
+            fn vertex() -> &'static str {
+                SCREEN_VERTEX
+            }
+
+            fn fragment() -> &'static str {
+                $fragment
+            }
+
+            fn draw_parameters() -> DrawParameters<'static> {
+                Default::default()
+            }
+        }
+    }
+}
+
+
+screen_shader!(TonemapShader, r#"
+    #version 330 core
+    uniform sampler2D scene;
+    uniform float exposure;
+    uniform float gamma;
+    in vec2 uv;
+    out vec4 f_color;
+
+    void main() {
+        vec4 color = texture(scene, uv);
+        vec3 mapped = vec3(1.0) - exp(-color.rgb * exposure);
+        f_color = vec4(pow(mapped, vec3(1.0 / gamma)), color.a);
+    }
+"#);
+
+screen_shader!(FxaaShader, r#"
+    #version 330 core
+    uniform sampler2D scene;
+    uniform vec2 resolution;
+    in vec2 uv;
+    out vec4 f_color;
+
+    const float SPAN_MAX = 8.0;
+    const float REDUCE_MUL = 1.0 / 8.0;
+    const float REDUCE_MIN = 1.0 / 128.0;
+    const vec3 LUMA = vec3(0.299, 0.587, 0.114);
+
+    void main() {
+        vec2 texel = 1.0 / resolution;
+        float nw = dot(texture(scene, uv + vec2(-1.0, -1.0) * texel).rgb, LUMA);
+        float ne = dot(texture(scene, uv + vec2(1.0, -1.0) * texel).rgb, LUMA);
+        float sw = dot(texture(scene, uv + vec2(-1.0, 1.0) * texel).rgb, LUMA);
+        float se = dot(texture(scene, uv + vec2(1.0, 1.0) * texel).rgb, LUMA);
+        vec4 center = texture(scene, uv);
+        float m = dot(center.rgb, LUMA);
+        float lo = min(m, min(min(nw, ne), min(sw, se)));
+        float hi = max(m, max(max(nw, ne), max(sw, se)));
+
+        vec2 dir = vec2(-((nw + ne) - (sw + se)), (nw + sw) - (ne + se));
+        float reduce = max((nw + ne + sw + se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+        float scale = 1.0 / (min(abs(dir.x), abs(dir.y)) + reduce);
+        dir = clamp(dir * scale, vec2(-SPAN_MAX), vec2(SPAN_MAX)) * texel;
+
+        vec3 a = 0.5 * (texture(scene, uv + dir * (1.0 / 3.0 - 0.5)).rgb +
+                        texture(scene, uv + dir * (2.0 / 3.0 - 0.5)).rgb);
+        vec3 b = a * 0.5 + 0.25 * (texture(scene, uv - dir * 0.5).rgb +
+                                   texture(scene, uv + dir * 0.5).rgb);
+        float luma_b = dot(b, LUMA);
+        f_color = vec4((luma_b < lo || luma_b > hi) ? a : b, center.a);
+    }
+"#);
+
+screen_shader!(BlurShader, r#"
+    #version 330 core
+    uniform sampler2D scene;
+    uniform vec2 direction;
+    in vec2 uv;
+    out vec4 f_color;
+
+    const float WEIGHT[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+    void main() {
+        vec4 color = texture(scene, uv) * WEIGHT[0];
+        for (int i = 1; i < 5; i++) {
+            color += texture(scene, uv + direction * float(i)) * WEIGHT[i];
+            color += texture(scene, uv - direction * float(i)) * WEIGHT[i];
+        }
+        f_color = color;
+    }
+"#);
+
+screen_shader!(BrightShader, r#"
+    #version 330 core
+    uniform sampler2D scene;
+    uniform float threshold;
+    in vec2 uv;
+    out vec4 f_color;
+
+    void main() {
+        vec3 color = texture(scene, uv).rgb;
+        float luma = dot(color, vec3(0.2126, 0.7152, 0.0722));
+        f_color = vec4(color * max(luma - threshold, 0.0) / max(luma, 0.0001), 1.0);
+    }
+"#);
+
+screen_shader!(CombineShader, r#"
+    #version 330 core
+    uniform sampler2D scene;
+    uniform sampler2D bloom;
+    uniform float intensity;
+    in vec2 uv;
+    out vec4 f_color;
+
+    void main() {
+        vec4 color = texture(scene, uv);
+        f_color = vec4(color.rgb + texture(bloom, uv).rgb * intensity, color.a);
+    }
+"#);
+
+screen_shader!(VignetteShader, r#"
+    #version 330 core
+    uniform sampler2D scene;
+    uniform float radius;
+    uniform float softness;
+    in vec2 uv;
+    out vec4 f_color;
+
+    void main() {
+        vec4 color = texture(scene, uv);
+        float v = smoothstep(radius, radius - softness, distance(uv, vec2(0.5)));
+        f_color = vec4(color.rgb * v, color.a);
+    }
+"#);
+
+
+/// Exposure tone mapping and gamma correction.
+pub struct Tonemap {
+    /// Exposure multiplier, default `1.0`.
+    pub exposure: f32,
+    /// Display gamma, default `2.2`.
+    pub gamma: f32,
+    pass: Pass<TonemapShader>,
+}
+
+
+impl Tonemap {
+    pub fn new(gfx: &Gfx) -> Result<Tonemap, Error> {
+        Ok(Tonemap {
+            exposure: 1.0,
+            gamma: 2.2,
+            pass: try!(Pass::new(gfx)),
+        })
+    }
+}
+
+
+impl Effect for Tonemap {
+    fn apply(&self, input: &Texture2d, output: &mut SimpleFrameBuffer) -> Result<(), Error> {
+        let uniforms = uniform! {
+            scene: clamped(input),
+            exposure: self.exposure,
+            gamma: self.gamma,
+        };
+        Ok(try!(self.pass.draw(output, &uniforms)))
+    }
+}
+
+
+/// Fast approximate anti-aliasing.
+pub struct Fxaa {
+    pass: Pass<FxaaShader>,
+}
+
+
+impl Fxaa {
+    pub fn new(gfx: &Gfx) -> Result<Fxaa, Error> {
+        Ok(Fxaa { pass: try!(Pass::new(gfx)) })
+    }
+}
+
+
+impl Effect for Fxaa {
+    fn apply(&self, input: &Texture2d, output: &mut SimpleFrameBuffer) -> Result<(), Error> {
+        let (w, h) = input.dimensions();
+        let uniforms = uniform! {
+            scene: clamped(input),
+            resolution: [w as f32, h as f32],
+        };
+        Ok(try!(self.pass.draw(output, &uniforms)))
+    }
+}
+
+
+/// Separable gaussian blur.
+pub struct Blur {
+    /// Distance between samples in pixels, default `1.0`.
+    pub radius: f32,
+    pass: Pass<BlurShader>,
+    temp: RenderTarget,
+}
+
+
+impl Blur {
+    pub fn new(gfx: &Gfx) -> Result<Blur, Error> {
+        Ok(Blur {
+            radius: 1.0,
+            pass: try!(Pass::new(gfx)),
+            temp: try!(buffer(gfx, gfx.dimensions())),
+        })
+    }
+
+    /// Blurs `input` into `output` through the temporary target.
+    fn blur(&self, input: &Texture2d, output: &mut SimpleFrameBuffer) -> Result<(), Error> {
+        let (w, h) = input.dimensions();
+        {
+            let mut temp = try!(self.temp.surface());
+            try!(self.pass.draw(&mut temp,
+                                &uniform! {
+                                    scene: clamped(input),
+                                    direction: [self.radius / w as f32, 0.0],
+                                }));
+        }
+        Ok(try!(self.pass.draw(output,
+                               &uniform! {
+                                   scene: clamped(self.temp.texture()),
+                                   direction: [0.0, self.radius / h as f32],
+                               })))
+    }
+}
+
+
+impl Effect for Blur {
+    fn apply(&self, input: &Texture2d, output: &mut SimpleFrameBuffer) -> Result<(), Error> {
+        self.blur(input, output)
+    }
+
+    fn resize(&mut self, dimensions: (u32, u32)) -> Result<(), Error> {
+        Ok(try!(self.temp.resize(dimensions)))
+    }
+}
+
+
+/// Adds a blurred copy of the bright areas.
+pub struct Bloom {
+    /// Luminance above which pixels glow, default `1.0`.
+    pub threshold: f32,
+    /// Glow strength, default `0.8`.
+    pub intensity: f32,
+    /// Glow blur.
+    pub blur: Blur,
+    bright: Pass<BrightShader>,
+    combine: Pass<CombineShader>,
+    bright_target: RenderTarget,
+    glow_target: RenderTarget,
+}
+
+
+impl Bloom {
+    pub fn new(gfx: &Gfx) -> Result<Bloom, Error> {
+        let mut blur = try!(Blur::new(gfx));
+        blur.radius = 2.0;
+        Ok(Bloom {
+            threshold: 1.0,
+            intensity: 0.8,
+            blur: blur,
+            bright: try!(Pass::new(gfx)),
+            combine: try!(Pass::new(gfx)),
+            bright_target: try!(buffer(gfx, gfx.dimensions())),
+            glow_target: try!(buffer(gfx, gfx.dimensions())),
+        })
+    }
+}
+
+
+impl Effect for Bloom {
+    fn apply(&self, input: &Texture2d, output: &mut SimpleFrameBuffer) -> Result<(), Error> {
+        {
+            let mut bright = try!(self.bright_target.surface());
+            try!(self.bright.draw(&mut bright,
+                                  &uniform! {
+                                      scene: clamped(input),
+                                      threshold: self.threshold,
+                                  }));
+        }
+        {
+            let mut glow = try!(self.glow_target.surface());
+            try!(self.blur.blur(self.bright_target.texture(), &mut glow));
+        }
+        let uniforms = uniform! {
+            scene: clamped(input),
+            bloom: clamped(self.glow_target.texture()),
+            intensity: self.intensity,
+        };
+        Ok(try!(self.combine.draw(output, &uniforms)))
+    }
+
+    fn resize(&mut self, dimensions: (u32, u32)) -> Result<(), Error> {
+        try!(self.blur.resize(dimensions));
+        try!(self.bright_target.resize(dimensions));
+        Ok(try!(self.glow_target.resize(dimensions)))
+    }
+}
+
+
+/// Darkens the screen corners.
+pub struct Vignette {
+    /// Distance from the center where darkening ends, default `0.75`.
+    pub radius: f32,
+    /// Width of the darkening gradient, default `0.45`.
+    pub softness: f32,
+    pass: Pass<VignetteShader>,
+}
+
+
+impl Vignette {
+    pub fn new(gfx: &Gfx) -> Result<Vignette, Error> {
+        Ok(Vignette {
+            radius: 0.75,
+            softness: 0.45,
+            pass: try!(Pass::new(gfx)),
+        })
+    }
+}
+
+
+impl Effect for Vignette {
+    fn apply(&self, input: &Texture2d, output: &mut SimpleFrameBuffer) -> Result<(), Error> {
+        let uniforms = uniform! {
+            scene: clamped(input),
+            radius: self.radius,
+            softness: self.softness,
+        };
+        Ok(try!(self.pass.draw(output, &uniforms)))
+    }
+}
+
+
+/// Errors which can occur when creating or running effects.
+#[derive(Debug)]
+pub enum Error {
+    /// Shader program create failure.
+    Program(ProgramCreationError),
+    /// Full-screen triangle create failure.
+    Vertex(VertexCreationError),
+    /// Offscreen target failure.
+    Target(TargetError),
+    /// Draw failure.
+    Draw(DrawError),
+}
+
+
+impl From<ProgramCreationError> for Error {
+    fn from(err: ProgramCreationError) -> Error {
+        Error::Program(err)
+    }
+}
+
+
+impl From<VertexCreationError> for Error {
+    fn from(err: VertexCreationError) -> Error {
+        Error::Vertex(err)
+    }
+}
+
+
+impl From<TargetError> for Error {
+    fn from(err: TargetError) -> Error {
+        Error::Target(err)
+    }
+}
+
+
+impl From<DrawError> for Error {
+    fn from(err: DrawError) -> Error {
+        Error::Draw(err)
+    }
+}
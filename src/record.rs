@@ -0,0 +1,469 @@
+//! Input recording and replay.
+//!
+//! A `Recorder` listens to the input events and stores them with the frame
+//! times of the loop. The `Recording` is saved to a text file, and a
+//! `Replayer` feeds it back to the loop in place of the live events, so a
+//! captured session runs the same simulation again.
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::Duration;
+//! use rier::Loop;
+//! use rier::main_loop::Return;
+//! use rier::event::Listener;
+//! use rier::input::{Input, InputEvent};
+//! use rier::gamepad::{GamepadEvent, GamepadId};
+//! use rier::record::{Recorder, Recording, Replayer};
+//!
+//! // Captured by listening to `Context::input_notifier`.
+//! let mut recorder = Recorder::new();
+//! recorder.on_event(&InputEvent::MouseMove(10, 20));
+//! let name = "Pad\nname ".to_string();
+//! recorder.on_event(&InputEvent::Gamepad(GamepadEvent::Connected(GamepadId(0), name)));
+//! recorder.end_frame(Duration::from_millis(16));
+//! recorder.on_event(&InputEvent::Text('a'));
+//! recorder.end_frame(Duration::from_millis(17));
+//!
+//! let text = recorder.recording().to_string();
+//! let recording: Recording = text.parse().unwrap();
+//! assert_eq!(&recording, recorder.recording());
+//!
+//! let mut replayer = Replayer::new(recording);
+//! let deltas = replayer.deltas();
+//! let mut input = Input::new();
+//! let mut typed = String::new();
+//! Loop::new(|_| {
+//!     // `replayer.pump(&gfx)` with a context.
+//!     input.begin_frame();
+//!     for event in replayer.next_frame() {
+//!         input.handle(&event);
+//!     }
+//!     typed.push_str(input.text());
+//!     Return::Next
+//! }).run(deltas).unwrap();
+//! assert_eq!(typed, "a");
+//! ```
+//!
+//! # File format
+//!
+//! A `frame <number> <nanoseconds>` line starts each frame, followed by a
+//! `<number> <event>` line for each input event of the frame. Typed text
+//! and gamepad names are written as Unicode code points, so any character
+//! fits on the line.
+use std::io::{self, Read, Write};
+use std::fs::File;
+use std::fmt;
+use std::mem;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+use std::collections::VecDeque;
+use context::{Context, Resize};
+use event::{Listener, Return, NotifyError};
+use input::{InputEvent, KeyEvent, Modifiers, MouseButton, ScrollDelta};
+use gamepad::{GamepadEvent, GamepadId};
+
+
+/// Unwraps an option or returns `None`.
+macro_rules! try_opt {
+    ($e:expr) => (match $e { Some(x) => x, None => return None })
+}
+
+
+/// Input events received during one frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// Frame time passed to the loop.
+    pub delta: Duration,
+    pub events: Vec<InputEvent>,
+}
+
+
+/// Recorded frames.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Recording {
+    pub frames: Vec<Frame>,
+}
+
+
+impl Recording {
+    pub fn new() -> Recording {
+        Recording::default()
+    }
+
+    /// Loads a recording file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Recording, Error> {
+        let mut text = String::new();
+        try!(try!(File::open(path)).read_to_string(&mut text));
+        text.parse()
+    }
+
+    /// Saves to a recording file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = try!(File::create(path));
+        file.write_all(self.to_string().as_bytes())
+    }
+}
+
+
+/// Records input events by frame.
+///
+/// Register it with a high priority, so it sees the events other callbacks
+/// consume, and call `end_frame` once per loop frame after pumping events.
+///
+/// ```no_run
+/// use std::rc::Rc;
+/// use std::cell::RefCell;
+/// use rier::{Context, Loop};
+/// use rier::main_loop::Return;
+/// use rier::record::Recorder;
+///
+/// let gfx = Context::create("Record", (800, 600)).gfx();
+/// let recorder = Rc::new(RefCell::new(Recorder::new()));
+/// gfx.input_notifier().listen_with_priority(i32::max_value(), recorder.clone());
+/// Loop::new(|time| {
+///     gfx.pump_events().unwrap();
+///     recorder.borrow_mut().end_frame(time.real_delta());
+///     if gfx.close_requested() {
+///         return Return::Exit;
+///     }
+///     Return::Next
+/// }).start().unwrap();
+/// recorder.borrow().recording().save("session.txt").unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Recorder {
+    recording: Recording,
+    pending: Vec<InputEvent>,
+}
+
+
+impl Recorder {
+    pub fn new() -> Recorder {
+        Recorder::default()
+    }
+
+    /// Stores the events received since the last call as a frame.
+    pub fn end_frame(&mut self, delta: Duration) {
+        let events = mem::replace(&mut self.pending, Vec::new());
+        self.recording.frames.push(Frame {
+            delta: delta,
+            events: events,
+        });
+    }
+
+    /// Frames recorded so far.
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    pub fn into_recording(self) -> Recording {
+        self.recording
+    }
+}
+
+
+impl Listener<InputEvent> for Recorder {
+    fn on_event(&mut self, event: &InputEvent) -> Return<InputEvent> {
+        self.pending.push(event.clone());
+        Return::Next
+    }
+}
+
+
+/// Feeds a recording back frame by frame.
+///
+/// Run the loop with `Loop::run(replayer.deltas())` so each frame gets its
+/// recorded frame time, and call `pump` in place of `Context::pump_events`.
+pub struct Replayer {
+    deltas: Vec<Duration>,
+    frames: VecDeque<Vec<InputEvent>>,
+}
+
+
+impl Replayer {
+    pub fn new(recording: Recording) -> Replayer {
+        Replayer {
+            deltas: recording.frames.iter().map(|frame| frame.delta).collect(),
+            frames: recording.frames.into_iter().map(|frame| frame.events).collect(),
+        }
+    }
+
+    /// Recorded frame times.
+    pub fn deltas(&self) -> Vec<Duration> {
+        self.deltas.clone()
+    }
+
+    /// Returns the events of the next frame, empty after the last frame.
+    pub fn next_frame(&mut self) -> Vec<InputEvent> {
+        self.frames.pop_front().unwrap_or_else(Vec::new)
+    }
+
+    /// Returns `true` if all frames were replayed.
+    pub fn is_finished(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Notifies the events of the next frame to the context input.
+    ///
+    /// Recorded resizes change the context size and notify its resize
+    /// callbacks, `Context::pump_events` then sets it back to the window
+    /// size.
+    pub fn pump(&mut self, ctx: &Context) -> Result<(), NotifyError> {
+        ctx.inject(self.next_frame())
+    }
+}
+
+
+impl fmt::Display for Recording {
+    /// Writes the file format.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (number, frame) in self.frames.iter().enumerate() {
+            let nanos = frame.delta.as_secs() * 1000000000 + frame.delta.subsec_nanos() as u64;
+            try!(writeln!(f, "frame {} {}", number, nanos));
+            for event in &frame.events {
+                try!(writeln!(f, "{} {}", number, event_line(event)));
+            }
+        }
+        Ok(())
+    }
+}
+
+
+impl FromStr for Recording {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Recording, Error> {
+        let mut recording = Recording::new();
+        for (index, line) in text.lines().enumerate() {
+            let error = |message: &str| Error::Parse(index + 1, message.to_string());
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let mut parts = line.splitn(2, ' ');
+            let first = parts.next().unwrap();
+            let rest = parts.next().unwrap_or("");
+            if first == "frame" {
+                let mut words = rest.split(' ');
+                let number = words.next().and_then(|n| n.parse::<usize>().ok());
+                let nanos = words.next().and_then(|n| n.parse::<u64>().ok());
+                match (number, nanos) {
+                    (Some(number), Some(nanos)) if number == recording.frames.len() => {
+                        recording.frames.push(Frame {
+                            delta: Duration::new(nanos / 1000000000, (nanos % 1000000000) as u32),
+                            events: Vec::new(),
+                        });
+                    }
+                    _ => return Err(error("bad frame line")),
+                }
+                continue;
+            }
+            if first.parse::<usize>().ok().map(|n| n + 1) != Some(recording.frames.len()) {
+                return Err(error("event is not in the current frame"));
+            }
+            let event = try!(parse_event(rest).ok_or_else(|| error("bad event")));
+            recording.frames.last_mut().unwrap().events.push(event);
+        }
+        Ok(recording)
+    }
+}
+
+
+fn modifiers_word(modifiers: &Modifiers) -> String {
+    let names = [(modifiers.shift, "Shift"),
+                 (modifiers.ctrl, "Ctrl"),
+                 (modifiers.alt, "Alt"),
+                 (modifiers.logo, "Logo")];
+    let held: Vec<&str> = names.iter().filter(|&&(held, _)| held).map(|&(_, name)| name).collect();
+    if held.is_empty() { "-".to_string() } else { held.join("+") }
+}
+
+
+fn parse_modifiers(word: &str) -> Option<Modifiers> {
+    let mut modifiers = Modifiers::default();
+    if word == "-" {
+        return Some(modifiers);
+    }
+    for name in word.split('+') {
+        match name {
+            "Shift" => modifiers.shift = true,
+            "Ctrl" => modifiers.ctrl = true,
+            "Alt" => modifiers.alt = true,
+            "Logo" => modifiers.logo = true,
+            _ => return None,
+        }
+    }
+    Some(modifiers)
+}
+
+
+fn button_word(button: &MouseButton) -> String {
+    match *button {
+        MouseButton::Other(n) => n.to_string(),
+        button => format!("{:?}", button),
+    }
+}
+
+
+fn parse_button(word: &str) -> Option<MouseButton> {
+    match word {
+        "Left" => Some(MouseButton::Left),
+        "Right" => Some(MouseButton::Right),
+        "Middle" => Some(MouseButton::Middle),
+        n => n.parse().ok().map(MouseButton::Other),
+    }
+}
+
+
+/// Code points joined by `,`, `-` if empty.
+fn name_word(name: &str) -> String {
+    if name.is_empty() {
+        return "-".to_string();
+    }
+    let points: Vec<String> = name.chars().map(|c| (c as u32).to_string()).collect();
+    points.join(",")
+}
+
+
+fn parse_name(word: &str) -> Option<String> {
+    if word == "-" {
+        return Some(String::new());
+    }
+    word.split(',')
+        .map(|point| point.parse().ok().and_then(::std::char::from_u32))
+        .collect()
+}
+
+
+fn key_line(kind: &str, event: &KeyEvent) -> String {
+    let key = event.key.map(|key| key.name()).unwrap_or("?");
+    format!("{} {} {} {}", kind, key, event.scancode, modifiers_word(&event.modifiers))
+}
+
+
+fn event_line(event: &InputEvent) -> String {
+    match *event {
+        InputEvent::KeyDown(ref e) => key_line("KeyDown", e),
+        InputEvent::KeyUp(ref e) => key_line("KeyUp", e),
+        InputEvent::Text(c) => format!("Text {}", c as u32),
+        InputEvent::MouseMove(x, y) => format!("MouseMove {} {}", x, y),
+        InputEvent::MouseDown(ref b) => format!("MouseDown {}", button_word(b)),
+        InputEvent::MouseUp(ref b) => format!("MouseUp {}", button_word(b)),
+        InputEvent::Scroll(ScrollDelta::Lines(x, y)) => format!("ScrollLines {} {}", x, y),
+        InputEvent::Scroll(ScrollDelta::Pixels(x, y)) => format!("ScrollPixels {} {}", x, y),
+        InputEvent::Focus(focused) => format!("Focus {}", focused),
+        InputEvent::Resize(ref r) => {
+            format!("Resize {} {} {}", r.dimensions.0, r.dimensions.1, r.hidpi_factor)
+        }
+        InputEvent::Close => "Close".to_string(),
+        InputEvent::Gamepad(ref e) => {
+            match *e {
+                GamepadEvent::Connected(id, ref name) => {
+                    format!("PadConnected {} {}", id.0, name_word(name))
+                }
+                GamepadEvent::Disconnected(id) => format!("PadDisconnected {}", id.0),
+                GamepadEvent::ButtonDown(id, b) => format!("PadDown {} {}", id.0, b.name()),
+                GamepadEvent::ButtonUp(id, b) => format!("PadUp {} {}", id.0, b.name()),
+                GamepadEvent::AxisMoved(id, a, v) => {
+                    format!("PadAxis {} {} {}", id.0, a.name(), v)
+                }
+            }
+        }
+    }
+}
+
+
+fn parse_event(line: &str) -> Option<InputEvent> {
+    let words: Vec<&str> = line.split(' ').collect();
+    let parse_key = |kind: &str| -> Option<KeyEvent> {
+        if words.len() != 4 || words[0] != kind {
+            return None;
+        }
+        let key = if words[1] == "?" {
+            None
+        } else {
+            match words[1].parse() {
+                Ok(key) => Some(key),
+                Err(_) => return None,
+            }
+        };
+        match (words[2].parse(), parse_modifiers(words[3])) {
+            (Ok(scancode), Some(modifiers)) => {
+                Some(KeyEvent {
+                    key: key,
+                    scancode: scancode,
+                    modifiers: modifiers,
+                })
+            }
+            _ => None,
+        }
+    };
+    let pad = |i: usize| words.get(i).and_then(|w| w.parse().ok()).map(GamepadId);
+    let event = match (words[0], words.len()) {
+        ("KeyDown", _) => InputEvent::KeyDown(try_opt!(parse_key("KeyDown"))),
+        ("KeyUp", _) => InputEvent::KeyUp(try_opt!(parse_key("KeyUp"))),
+        ("Text", 2) => {
+            InputEvent::Text(try_opt!(words[1].parse().ok().and_then(::std::char::from_u32)))
+        }
+        ("MouseMove", 3) => {
+            InputEvent::MouseMove(try_opt!(words[1].parse().ok()), try_opt!(words[2].parse().ok()))
+        }
+        ("MouseDown", 2) => InputEvent::MouseDown(try_opt!(parse_button(words[1]))),
+        ("MouseUp", 2) => InputEvent::MouseUp(try_opt!(parse_button(words[1]))),
+        ("ScrollLines", 3) => {
+            InputEvent::Scroll(ScrollDelta::Lines(try_opt!(words[1].parse().ok()),
+                                                  try_opt!(words[2].parse().ok())))
+        }
+        ("ScrollPixels", 3) => {
+            InputEvent::Scroll(ScrollDelta::Pixels(try_opt!(words[1].parse().ok()),
+                                                   try_opt!(words[2].parse().ok())))
+        }
+        ("Focus", 2) => InputEvent::Focus(try_opt!(words[1].parse().ok())),
+        ("Resize", 4) => {
+            InputEvent::Resize(Resize {
+                dimensions: (try_opt!(words[1].parse().ok()), try_opt!(words[2].parse().ok())),
+                hidpi_factor: try_opt!(words[3].parse().ok()),
+            })
+        }
+        ("Close", 1) => InputEvent::Close,
+        ("PadConnected", 3) => {
+            InputEvent::Gamepad(GamepadEvent::Connected(try_opt!(pad(1)),
+                                                        try_opt!(parse_name(words[2]))))
+        }
+        ("PadDisconnected", 2) => InputEvent::Gamepad(GamepadEvent::Disconnected(try_opt!(pad(1)))),
+        ("PadDown", 3) => {
+            InputEvent::Gamepad(GamepadEvent::ButtonDown(try_opt!(pad(1)),
+                                                         try_opt!(words[2].parse().ok())))
+        }
+        ("PadUp", 3) => {
+            InputEvent::Gamepad(GamepadEvent::ButtonUp(try_opt!(pad(1)),
+                                                       try_opt!(words[2].parse().ok())))
+        }
+        ("PadAxis", 4) => {
+            InputEvent::Gamepad(GamepadEvent::AxisMoved(try_opt!(pad(1)),
+                                                        try_opt!(words[2].parse().ok()),
+                                                        try_opt!(words[3].parse().ok())))
+        }
+        _ => return None,
+    };
+    Some(event)
+}
+
+
+/// Errors which can occur when loading a recording.
+#[derive(Debug)]
+pub enum Error {
+    /// File read failure.
+    Io(io::Error),
+    /// Syntax error, with the line number and a message.
+    Parse(usize, String),
+}
+
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
@@ -4,7 +4,7 @@ use std::default::Default;
 use glium::{Program, DrawParameters, Blend};
 use glium::uniforms::Uniforms;
 use mesh::{Mesh, Vertex};
-use context::{Gfx, Surface, DrawError};
+use context::{Gfx, Surface, DrawError, FrameGuard};
 
 pub use glium::index::PrimitiveType;
 pub use glium::program::ProgramCreationError;
@@ -34,10 +34,13 @@ impl<S: Shader> Renderer<S> {
         })
     }
 
-    /// Draw with current frame.
-    pub fn draw<U: Uniforms>(&self, mesh: &Mesh<S::Vertex>, uniforms: &U) -> Result<(), DrawError> {
-        let mut target = self.gfx.get_frame_mut();
-        self.draw_with_target(&mut *target, mesh, uniforms)
+    /// Draw with the frame.
+    pub fn draw<U: Uniforms>(&self,
+                             frame: &mut FrameGuard,
+                             mesh: &Mesh<S::Vertex>,
+                             uniforms: &U)
+                             -> Result<(), DrawError> {
+        self.draw_with_target(&mut **frame, mesh, uniforms)
     }
 
 
@@ -0,0 +1,232 @@
+//! Timers and delayed callbacks.
+//!
+//! The main loop advances a `Scheduler` with game time, so timers stop
+//! while the clock is paused.
+//!
+//! Callbacks run in order of due time, callbacks due at the same time run
+//! in the order they were scheduled. A callback scheduled while the
+//! scheduler is advancing runs no earlier than the next advance.
+//!
+//! # Example
+//!
+//! ```
+//! use std::rc::Rc;
+//! use std::cell::Cell;
+//! use std::time::Duration;
+//! use rier::schedule::Scheduler;
+//!
+//! let fired = Rc::new(Cell::new(0));
+//! let mut schedule = Scheduler::new();
+//! let counter = fired.clone();
+//! let timer = schedule.every(Duration::from_millis(100), move |_| {
+//!     counter.set(counter.get() + 1);
+//! });
+//!
+//! schedule.advance(Duration::from_millis(250));
+//! assert_eq!(fired.get(), 2);
+//! schedule.cancel(timer);
+//! schedule.advance(Duration::from_millis(250));
+//! assert_eq!(fired.get(), 2);
+//! ```
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+use std::time::Duration;
+
+
+/// Callback function, may schedule or cancel timers.
+pub type Callback = Box<FnMut(&mut Scheduler)>;
+
+
+/// Handle of a scheduled callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Timer(u64);
+
+
+struct Entry {
+    due: Duration,
+    id: u64,
+    /// Advance count when scheduled.
+    frame: u64,
+    interval: Option<Duration>,
+    callback: Callback,
+}
+
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Entry) -> bool {
+        self.due == other.due && self.id == other.id
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Entry) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    // Reversed, the heap pops the earliest entry first.
+    fn cmp(&self, other: &Entry) -> Ordering {
+        (other.due, other.id).cmp(&(self.due, self.id))
+    }
+}
+
+
+/// Runs callbacks after delays or at intervals.
+pub struct Scheduler {
+    now: Duration,
+    frame: u64,
+    next_id: u64,
+    queue: BinaryHeap<Entry>,
+    pending: HashSet<u64>,
+    cancelled: HashSet<u64>,
+}
+
+
+impl Scheduler {
+    pub fn new() -> Scheduler {
+        Scheduler {
+            now: Duration::new(0, 0),
+            frame: 0,
+            next_id: 0,
+            queue: BinaryHeap::new(),
+            pending: HashSet::new(),
+            cancelled: HashSet::new(),
+        }
+    }
+
+    fn push(&mut self, delay: Duration, interval: Option<Duration>, callback: Callback) -> Timer {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.pending.insert(id);
+        self.queue.push(Entry {
+            due: self.now + delay,
+            id: id,
+            frame: self.frame,
+            interval: interval,
+            callback: callback,
+        });
+        Timer(id)
+    }
+
+    /// Calls the function once after the delay.
+    pub fn after<F>(&mut self, delay: Duration, f: F) -> Timer
+        where F: 'static + FnMut(&mut Scheduler)
+    {
+        self.push(delay, None, Box::new(f))
+    }
+
+    /// Calls the function every interval, first after one interval.
+    ///
+    /// A zero interval calls it every advance, like `next_frame` also while
+    /// the clock is paused.
+    pub fn every<F>(&mut self, interval: Duration, f: F) -> Timer
+        where F: 'static + FnMut(&mut Scheduler)
+    {
+        self.push(interval, Some(interval), Box::new(f))
+    }
+
+    /// Calls the function once on the next advance.
+    ///
+    /// It does not wait for time to pass: a variable timestep main loop
+    /// advances by zero every frame while the clock is paused, so the
+    /// function runs while paused too. Use `after` with a small delay to
+    /// wait for game time.
+    pub fn next_frame<F>(&mut self, f: F) -> Timer
+        where F: 'static + FnMut(&mut Scheduler)
+    {
+        self.push(Duration::new(0, 0), None, Box::new(f))
+    }
+
+    /// Cancels a callback, returns `false` if it already finished.
+    pub fn cancel(&mut self, timer: Timer) -> bool {
+        let Timer(id) = timer;
+        if self.pending.remove(&id) {
+            self.cancelled.insert(id);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns `true` if the callback will be called again.
+    pub fn is_pending(&self, timer: Timer) -> bool {
+        let Timer(id) = timer;
+        self.pending.contains(&id)
+    }
+
+    /// Number of pending callbacks.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Cancels all callbacks.
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.pending.clear();
+        self.cancelled.clear();
+    }
+
+    /// Time advanced since creation.
+    pub fn now(&self) -> Duration {
+        self.now
+    }
+
+    /// Advances time and runs the callbacks that became due.
+    pub fn advance(&mut self, delta: Duration) {
+        self.frame += 1;
+        self.now += delta;
+        let mut deferred = Vec::new();
+        while let Some(mut entry) = self.queue.pop() {
+            if entry.due > self.now {
+                self.queue.push(entry);
+                break;
+            }
+            if self.cancelled.remove(&entry.id) {
+                continue;
+            }
+            if entry.frame == self.frame {
+                // Scheduled during this advance.
+                deferred.push(entry);
+                continue;
+            }
+            if entry.interval.is_none() {
+                self.pending.remove(&entry.id);
+            }
+            (entry.callback)(self);
+            if let Some(interval) = entry.interval {
+                if !self.pending.contains(&entry.id) {
+                    // Cancelled by a callback.
+                    self.cancelled.remove(&entry.id);
+                    continue;
+                }
+                entry.due += interval;
+                if interval == Duration::new(0, 0) {
+                    entry.frame = self.frame;
+                }
+                self.queue.push(entry);
+            }
+        }
+        for entry in deferred {
+            if self.pending.contains(&entry.id) {
+                self.queue.push(entry);
+            } else {
+                // Cancelled by a later callback.
+                self.cancelled.remove(&entry.id);
+            }
+        }
+    }
+}
+
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
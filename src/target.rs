@@ -0,0 +1,239 @@
+//! Offscreen render targets.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rier::{Context, RenderTarget};
+//! use rier::context::Surface;
+//!
+//! let gfx = Context::headless((800, 600)).gfx();
+//! let target = RenderTarget::new(&gfx).unwrap();
+//! {
+//!     let mut surface = target.surface().unwrap();
+//!     surface.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
+//!     // renderer.draw_with_target(&mut surface, &mesh, &uniforms)
+//! }
+//! // Sample `target.texture()` in a later pass.
+//! ```
+//!
+//! Multisampled targets draw into multisample textures, call `resolve` to
+//! copy them into the textures that can be sampled.
+use glium::texture::{Texture2d, DepthTexture2d, UncompressedFloatFormat, MipmapsOption,
+                     TextureCreationError};
+use glium::texture::{Texture2dMultisample, DepthTexture2dMultisample};
+use glium::framebuffer::{SimpleFrameBuffer, MultiOutputFrameBuffer, ValidationError};
+use glium::uniforms::MagnifySamplerFilter;
+use context::{Gfx, Surface};
+
+
+/// Colour and depth textures that can be drawn into.
+pub struct RenderTarget {
+    gfx: Gfx,
+    format: UncompressedFloatFormat,
+    samples: u32,
+    outputs: Vec<(String, Texture2d)>,
+    /// `None` when multisampling, depth is drawn into the multisample
+    /// texture.
+    depth: Option<DepthTexture2d>,
+    multisample: Option<Multisample>,
+}
+
+
+/// Attachments drawn into when multisampling is enabled.
+struct Multisample {
+    outputs: Vec<Texture2dMultisample>,
+    depth: DepthTexture2dMultisample,
+}
+
+
+impl RenderTarget {
+    /// Creates a target of the context framebuffer size with one RGBA colour
+    /// texture.
+    pub fn new(gfx: &Gfx) -> Result<RenderTarget, TargetError> {
+        RenderTarget::with_dimensions(gfx, gfx.dimensions())
+    }
+
+    /// Creates a target of the given size with one RGBA colour texture.
+    pub fn with_dimensions(gfx: &Gfx, dimensions: (u32, u32)) -> Result<RenderTarget, TargetError> {
+        RenderTarget::with_outputs(gfx, dimensions, &["color"], UncompressedFloatFormat::U8U8U8U8)
+    }
+
+    /// Creates a target with a colour texture for each fragment shader output.
+    ///
+    /// Use `multi_surface` to draw into all outputs at once. Fails with
+    /// `TargetError::NoOutputs` if `outputs` is empty.
+    pub fn with_outputs(gfx: &Gfx,
+                        dimensions: (u32, u32),
+                        outputs: &[&str],
+                        format: UncompressedFloatFormat)
+                        -> Result<RenderTarget, TargetError> {
+        RenderTarget::with_samples(gfx, dimensions, outputs, format, 0)
+    }
+
+    /// Creates a multisampled target, `0` samples disables multisampling.
+    pub fn with_samples(gfx: &Gfx,
+                        dimensions: (u32, u32),
+                        outputs: &[&str],
+                        format: UncompressedFloatFormat,
+                        samples: u32)
+                        -> Result<RenderTarget, TargetError> {
+        if outputs.is_empty() {
+            return Err(TargetError::NoOutputs);
+        }
+        let (w, h) = dimensions;
+        let display = &gfx.display;
+        let mut textures = Vec::with_capacity(outputs.len());
+        for name in outputs {
+            let texture = try!(Texture2d::empty_with_format(display,
+                                                            format,
+                                                            MipmapsOption::NoMipmap,
+                                                            w,
+                                                            h));
+            textures.push((name.to_string(), texture));
+        }
+        let mut depth = None;
+        let multisample = if samples > 0 {
+            let mut textures = Vec::with_capacity(outputs.len());
+            for _ in outputs {
+                let texture = try!(Texture2dMultisample::empty_with_format(display,
+                                                                           format,
+                                                                           MipmapsOption::NoMipmap,
+                                                                           w,
+                                                                           h,
+                                                                           samples));
+                textures.push(texture);
+            }
+            Some(Multisample {
+                outputs: textures,
+                depth: try!(DepthTexture2dMultisample::empty(display, w, h, samples)),
+            })
+        } else {
+            depth = Some(try!(DepthTexture2d::empty(display, w, h)));
+            None
+        };
+        Ok(RenderTarget {
+            gfx: gfx.clone(),
+            format: format,
+            samples: samples,
+            outputs: textures,
+            depth: depth,
+            multisample: multisample,
+        })
+    }
+
+    /// Returns the sample count, `0` if not multisampled.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// Returns the size in pixels.
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.texture().dimensions()
+    }
+
+    /// Recreates the textures if the size changed, contents are lost.
+    pub fn resize(&mut self, dimensions: (u32, u32)) -> Result<(), TargetError> {
+        if dimensions == self.dimensions() {
+            return Ok(());
+        }
+        let names: Vec<String> = self.outputs.iter().map(|&(ref name, _)| name.clone()).collect();
+        let names: Vec<&str> = names.iter().map(|name| &**name).collect();
+        let target = try!(RenderTarget::with_samples(&self.gfx,
+                                                     dimensions,
+                                                     &names,
+                                                     self.format,
+                                                     self.samples));
+        *self = target;
+        Ok(())
+    }
+
+    /// The first colour texture.
+    pub fn texture(&self) -> &Texture2d {
+        &self.outputs[0].1
+    }
+
+    /// Colour texture of the named output.
+    pub fn output(&self, name: &str) -> Option<&Texture2d> {
+        self.outputs.iter().find(|&&(ref n, _)| n == name).map(|&(_, ref texture)| texture)
+    }
+
+    /// The depth texture, `None` if multisampled.
+    pub fn depth(&self) -> Option<&DepthTexture2d> {
+        self.depth.as_ref()
+    }
+
+    fn single_depth(&self) -> &DepthTexture2d {
+        self.depth.as_ref().expect("target without multisampling has a depth texture")
+    }
+
+    /// Surface that draws into the first colour texture.
+    pub fn surface(&self) -> Result<SimpleFrameBuffer, TargetError> {
+        let display = &self.gfx.display;
+        let surface = match self.multisample {
+            Some(ref ms) => SimpleFrameBuffer::with_depth_buffer(display, &ms.outputs[0], &ms.depth),
+            None => {
+                SimpleFrameBuffer::with_depth_buffer(display, self.texture(), self.single_depth())
+            }
+        };
+        Ok(try!(surface))
+    }
+
+    /// Surface that draws into all colour textures by output name.
+    pub fn multi_surface(&self) -> Result<MultiOutputFrameBuffer, TargetError> {
+        let display = &self.gfx.display;
+        let names = self.outputs.iter().map(|&(ref name, _)| &**name);
+        let surface = match self.multisample {
+            Some(ref ms) => {
+                MultiOutputFrameBuffer::with_depth_buffer(display,
+                                                          names.zip(ms.outputs.iter()),
+                                                          &ms.depth)
+            }
+            None => {
+                let textures = self.outputs.iter().map(|&(_, ref texture)| texture);
+                MultiOutputFrameBuffer::with_depth_buffer(display,
+                                                          names.zip(textures),
+                                                          self.single_depth())
+            }
+        };
+        Ok(try!(surface))
+    }
+
+    /// Copies the multisampled colour into the sampleable textures.
+    ///
+    /// Does nothing if the target is not multisampled.
+    pub fn resolve(&self) -> Result<(), TargetError> {
+        if let Some(ref ms) = self.multisample {
+            for (source, &(_, ref texture)) in ms.outputs.iter().zip(self.outputs.iter()) {
+                let source = try!(SimpleFrameBuffer::new(&self.gfx.display, source));
+                source.fill(&texture.as_surface(), MagnifySamplerFilter::Nearest);
+            }
+        }
+        Ok(())
+    }
+}
+
+
+/// Errors which can occur when creating or drawing a render target.
+#[derive(Debug)]
+pub enum TargetError {
+    /// Texture create failure.
+    Texture(TextureCreationError),
+    /// Framebuffer create failure.
+    Framebuffer(ValidationError),
+    /// No colour output was given.
+    NoOutputs,
+}
+
+
+impl From<TextureCreationError> for TargetError {
+    fn from(err: TextureCreationError) -> TargetError {
+        TargetError::Texture(err)
+    }
+}
+
+
+impl From<ValidationError> for TargetError {
+    fn from(err: ValidationError) -> TargetError {
+        TargetError::Framebuffer(err)
+    }
+}
@@ -1,6 +1,9 @@
 //! Utility functions.
 use std::cell::UnsafeCell;
 use cgmath::Matrix4;
+use glium::texture::RawImage2d;
+use image::RgbaImage;
+use image::imageops::flip_vertical;
 
 /// 4x4 float matrix.
 pub type Matrix = Matrix4<f32>;
@@ -74,3 +77,11 @@ impl AsMatrix for Matrix {
         self
     }
 }
+
+
+/// Converts pixels read from OpenGL into a top-to-bottom image.
+pub fn raw_to_image(raw: RawImage2d<u8>) -> RgbaImage {
+    let image = RgbaImage::from_raw(raw.width, raw.height, raw.data.into_owned()).unwrap();
+    // OpenGL stores rows from bottom to top.
+    flip_vertical(&image)
+}